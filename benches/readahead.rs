@@ -0,0 +1,46 @@
+//! Compares the time taken to copy a large file with and without readahead.
+//!
+//! The file is created in the system's temporary directory. For meaningful results, the OS's
+//! page cache should be dropped before each run (on Linux,
+//! `sync; echo 3 | sudo tee /proc/sys/vm/drop_caches`), otherwise the file is read from memory
+//! and readahead makes no difference.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    time::Instant,
+};
+
+use transfer_progress::TransferBuilder;
+
+/// 512 MiB
+const FILE_SIZE: usize = 512 * 1024 * 1024;
+
+fn time_copy(path: &std::path::Path, readahead: bool) -> io::Result<()> {
+    let start = Instant::now();
+    let transfer = TransferBuilder::new()
+        .readahead(readahead)
+        .build(File::open(path)?, io::sink());
+    transfer.finish()?;
+    println!(
+        "readahead {}: {:?}",
+        if readahead { "on" } else { "off" },
+        start.elapsed()
+    );
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let path = std::env::temp_dir().join("transfer-progress-readahead-bench");
+    let mut file = File::create(&path)?;
+    let chunk = vec![0xa5; 1024 * 1024];
+    for _ in 0..FILE_SIZE / chunk.len() {
+        file.write_all(&chunk)?;
+    }
+    file.sync_all()?;
+    drop(file);
+
+    let res = time_copy(&path, false).and_then(|_| time_copy(&path, true));
+    fs::remove_file(&path)?;
+    res
+}
@@ -0,0 +1,393 @@
+use std::{
+    future::{self, Future},
+    io,
+    pin::Pin,
+    sync::{atomic::Ordering, Arc},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::watch,
+    task::JoinHandle,
+    time::{Interval, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    state::{CompleteOnPanic, TransferState},
+    ProgressHandle, ProgressSnapshot, TransferConfig, TransferError,
+};
+
+/// The size of the buffer used to copy data, the same as [`tokio::io::copy`]'s.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Monitors the progress of a transfer from an [asynchronous reader][AsyncRead] to an
+/// [asynchronous writer][AsyncWrite].
+///
+/// The transfer runs as a Tokio task, rather than on its own thread. Dropping an `AsyncTransfer`
+/// doesn't cancel the transfer: it keeps running in the background until the reader is exhausted,
+/// an error occurs, or it's [cancelled][AsyncTransfer::cancel].
+///
+/// An `AsyncTransfer` is a [`Future`] which resolves to the same result as
+/// [`finish`][AsyncTransfer::finish], so it can be awaited directly. To keep monitoring its
+/// progress while awaiting it, take a [`progress_handle`][AsyncTransfer::progress_handle] first.
+/// # Example
+/// ```no_run
+/// use transfer_progress::AsyncTransfer;
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let reader = tokio::fs::File::open("file1.txt").await?;
+/// let writer = tokio::fs::File::create("file2.txt").await?;
+/// let transfer = AsyncTransfer::new(reader, writer);
+/// let progress = transfer.progress_handle();
+/// tokio::spawn(async move {
+///     while !progress.is_complete() {
+///         println!("{} bytes", progress.transferred());
+///         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+///     }
+/// });
+/// let (reader, writer) = transfer.await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncTransfer<R, W> {
+    state: Arc<TransferState>,
+    /// Holds the latest snapshot for [`watch_progress`][AsyncTransfer::watch_progress].
+    progress: Arc<watch::Sender<ProgressSnapshot>>,
+    handle: JoinHandle<Result<(R, W), TransferError>>,
+}
+
+impl<R, W> AsyncTransfer<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Creates and starts a new `AsyncTransfer`.
+    ///
+    /// This must be called from within a Tokio runtime.
+    /// # Example
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader: &[u8] = &[0; 1024];
+    /// let transfer = AsyncTransfer::new(reader, tokio::io::sink());
+    /// transfer.finish().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(reader: R, writer: W) -> Self {
+        let state = Arc::new(TransferState::new(&TransferConfig::default(), None));
+        let (progress, _) = watch::channel(state.snapshot());
+        let progress = Arc::new(progress);
+        let cancelled = CancellationToken::new();
+        state.set_cancel_hook({
+            let cancelled = cancelled.clone();
+            move || cancelled.cancel()
+        });
+        let handle = tokio::spawn(copy(
+            Arc::clone(&state),
+            Arc::clone(&progress),
+            cancelled,
+            reader,
+            writer,
+        ));
+        Self {
+            state,
+            progress,
+            handle,
+        }
+    }
+
+    /// Consumes the `AsyncTransfer`, waiting until the transfer is complete.
+    ///
+    /// If the transfer was successful, returns `Ok(reader, writer)`, otherwise returns
+    /// the error. If the task performing the transfer panicked, returns
+    /// [`TransferError::Panicked`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader: &[u8] = b"Hello, world!";
+    /// let transfer = AsyncTransfer::new(reader, Vec::new());
+    /// let (_, writer) = transfer.finish().await?;
+    /// assert_eq!(writer, b"Hello, world!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// A transfer whose reader or writer panics still completes, having failed:
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// use std::{io, pin::Pin, task::{Context, Poll}};
+    /// use tokio::io::{AsyncRead, ReadBuf};
+    /// struct Broken;
+    /// impl AsyncRead for Broken {
+    ///     fn poll_read(
+    ///         self: Pin<&mut Self>,
+    ///         _: &mut Context<'_>,
+    ///         _: &mut ReadBuf<'_>,
+    ///     ) -> Poll<io::Result<()>> {
+    ///         panic!("the reader is broken");
+    ///     }
+    /// }
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let transfer = AsyncTransfer::new(Broken, Vec::new());
+    /// let progress = transfer.progress_handle();
+    /// assert!(transfer.finish().await.is_err());
+    /// assert!(progress.is_complete());
+    /// assert!(progress.is_failed());
+    /// # }
+    /// ```
+    pub async fn finish(self) -> io::Result<(R, W)> {
+        self.await
+    }
+
+    /// Asks the transfer to stop as soon as possible, even if it's waiting to read or write, after
+    /// which [`finish`][Self::finish] returns [`TransferError::Cancelled`]. If the transfer is
+    /// already complete, this does nothing.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{AsyncTransfer, TransferError};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// // A reader which never has anything to read
+    /// let (reader, _writer) = tokio::io::duplex(64);
+    /// let transfer = AsyncTransfer::new(reader, tokio::io::sink());
+    /// let progress = transfer.progress_handle();
+    /// transfer.cancel();
+    /// let error = transfer.await.err().unwrap();
+    /// let error = error.into_inner().unwrap().downcast::<TransferError>().unwrap();
+    /// assert!(matches!(*error, TransferError::Cancelled));
+    /// assert!(progress.is_cancelled());
+    /// # }
+    /// ```
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the elapsed time since the transfer started.
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer.
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring this transfer, which doesn't depend on the
+    /// types of its reader and writer, and remains usable after the transfer has been awaited.
+    /// # Example
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader: &[u8] = &[0; 1024];
+    /// let transfer = AsyncTransfer::new(reader, tokio::io::sink());
+    /// let progress = transfer.progress_handle();
+    /// transfer.await?;
+    /// assert!(progress.is_complete());
+    /// assert_eq!(progress.transferred(), 1024);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+
+    /// Returns a [`watch::Receiver`] which always holds a snapshot of the transfer's latest
+    /// statistics, and is notified each time a chunk is transferred, and once the transfer is
+    /// complete.
+    ///
+    /// Any number of receivers can be created, or cloned from one another, to share the
+    /// transfer's progress with many tasks at once.
+    /// # Example
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader: &[u8] = &[0; 1024];
+    /// let transfer = AsyncTransfer::new(reader, tokio::io::sink());
+    /// let mut progress = transfer.watch_progress();
+    /// tokio::spawn(async move {
+    ///     while progress.changed().await.is_ok() {
+    ///         println!("{} bytes", progress.borrow().transferred);
+    ///     }
+    /// });
+    /// transfer.finish().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_progress(&self) -> watch::Receiver<ProgressSnapshot> {
+        self.progress.subscribe()
+    }
+
+    /// Returns a [`Stream`] of snapshots of the transfer's statistics, one straight away, then
+    /// one every `interval`, ending after the first snapshot taken once the transfer is complete.
+    ///
+    /// This must be called from within a Tokio runtime. If the stream isn't polled for a while,
+    /// the snapshots which were missed are skipped, rather than delivered all at once.
+    /// # Panics
+    /// Panics if `interval` is zero.
+    /// # Example
+    /// ```
+    /// use transfer_progress::AsyncTransfer;
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader: &[u8] = &[0; 1024];
+    /// let transfer = AsyncTransfer::new(reader, tokio::io::sink());
+    /// let mut stream = transfer.progress_stream(Duration::from_millis(10));
+    /// let mut last = None;
+    /// while let Some(snapshot) = stream.next().await {
+    ///     println!("{} bytes", snapshot.transferred);
+    ///     last = Some(snapshot);
+    /// }
+    /// assert!(last.unwrap().complete);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn progress_stream(&self, interval: Duration) -> impl Stream<Item = ProgressSnapshot> {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ProgressStream {
+            state: Arc::clone(&self.state),
+            interval,
+            done: false,
+        }
+    }
+}
+
+impl<R, W> Future for AsyncTransfer<R, W> {
+    type Output = io::Result<(R, W)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(result)) => result,
+            Poll::Ready(Err(e)) if e.is_panic() => Err(TransferError::from_panic(e.into_panic())),
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(io::Error::other(e))),
+        };
+        Poll::Ready(result.map_err(Into::into))
+    }
+}
+
+/// The stream returned by [`AsyncTransfer::progress_stream`].
+struct ProgressStream {
+    state: Arc<TransferState>,
+    interval: Interval,
+    /// Set once a snapshot of the complete transfer has been yielded.
+    done: bool,
+}
+
+impl Stream for ProgressStream {
+    type Item = ProgressSnapshot;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.interval.poll_tick(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let snapshot = self.state.snapshot();
+        self.done = snapshot.complete;
+        Poll::Ready(Some(snapshot))
+    }
+}
+
+/// Copies everything from `reader` to `writer`, keeping `state` up to date, then marks the
+/// transfer as complete.
+async fn copy<R, W>(
+    state: Arc<TransferState>,
+    progress: Arc<watch::Sender<ProgressSnapshot>>,
+    cancelled: CancellationToken,
+    mut reader: R,
+    mut writer: W,
+) -> Result<(R, W), TransferError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // The runtime drops the task while still unwinding if the reader or writer panics
+    let _complete = CompleteOnPanic(&state);
+    let res = copy_loop(&state, &progress, &cancelled, &mut reader, &mut writer).await;
+    match &res {
+        Err(TransferError::Cancelled) => {
+            state.cancelled.store(true, Ordering::Release);
+            state.failed.store(true, Ordering::Release);
+        }
+        Err(e) => state.set_failed(e),
+        Ok(()) => {}
+    }
+    state.set_complete();
+    progress.send_replace(state.snapshot());
+    res.map(|_| (reader, writer))
+}
+
+async fn copy_loop<R, W>(
+    state: &TransferState,
+    progress: &watch::Sender<ProgressSnapshot>,
+    cancelled: &CancellationToken,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), TransferError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let len = match until_cancelled(cancelled, reader.read(&mut buf)).await? {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        state.add_read(len as u64);
+        until_cancelled(cancelled, writer.write_all(&buf[..len])).await??;
+        state.add_written(len as u64);
+        state.chunks.fetch_add(1, Ordering::Release);
+        // Don't bother taking a snapshot nobody will see
+        if progress.receiver_count() > 0 {
+            progress.send_replace(state.snapshot());
+        }
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Runs `future` to completion, unless `cancelled` is cancelled first, in which case returns
+/// [`TransferError::Cancelled`].
+async fn until_cancelled<F: Future>(
+    cancelled: &CancellationToken,
+    future: F,
+) -> Result<F::Output, TransferError> {
+    let cancelled = cancelled.cancelled();
+    tokio::pin!(cancelled, future);
+    future::poll_fn(|cx| {
+        // Check for cancellation first, so a transfer which never blocks still stops
+        if cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(TransferError::Cancelled));
+        }
+        future.as_mut().poll(cx).map(Ok)
+    })
+    .await
+}
@@ -0,0 +1,116 @@
+use std::{
+    io::{self, prelude::*},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{io::AsyncWrite, task::JoinHandle};
+use tokio_util::io::SyncIoBridge;
+
+use crate::{state::TransferState, TransferBuilder, TransferError};
+
+/// Creates and starts a new [`BridgeTransfer`] from a synchronous [reader][Read] to an
+/// [asynchronous writer][AsyncWrite].
+///
+/// This must be called from within a Tokio runtime. See [`BridgeTransfer`] for details.
+/// # Example
+/// ```no_run
+/// use transfer_progress::bridge_transfer;
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let reader = std::fs::File::open("file1.txt")?;
+/// let writer = tokio::fs::File::create("file2.txt").await?;
+/// let transfer = bridge_transfer(reader, writer);
+/// let (reader, writer) = transfer.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn bridge_transfer<R, W>(reader: R, writer: W) -> BridgeTransfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    TransferBuilder::new().build_bridge(reader, writer)
+}
+
+/// Monitors the progress of a transfer from a synchronous [reader][Read] to an
+/// [asynchronous writer][AsyncWrite].
+///
+/// The transfer runs on Tokio's blocking thread pool, since reading may block, and writes are
+/// driven on the runtime the transfer was created in. Dropping a `BridgeTransfer` doesn't cancel
+/// the transfer: it keeps running in the background until the reader is exhausted or an error
+/// occurs, occupying a blocking thread until then.
+pub struct BridgeTransfer<R, W> {
+    state: Arc<TransferState>,
+    handle: JoinHandle<Result<(R, SyncIoBridge<W>), TransferError>>,
+}
+
+impl<R, W> BridgeTransfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    pub(crate) fn with_builder(builder: TransferBuilder, reader: R, writer: W) -> Self {
+        let parts = builder.into_parts();
+        let (state, options) = (parts.state, parts.options);
+        let state_clone = Arc::clone(&state);
+        let writer = SyncIoBridge::new(writer);
+        let handle = tokio::task::spawn_blocking(move || {
+            crate::copy::copy(&state_clone, options, reader, writer).and_then(
+                |(reader, mut writer)| {
+                    writer.flush()?;
+                    Ok((reader, writer))
+                },
+            )
+        });
+        Self { state, handle }
+    }
+
+    /// Consumes the `BridgeTransfer`, waiting until the transfer is complete.
+    ///
+    /// If the transfer was successful, returns `Ok(reader, writer)`, otherwise returns
+    /// the error.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::bridge_transfer;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader = std::fs::File::open("file1.txt")?;
+    /// let writer = tokio::fs::File::create("file2.txt").await?;
+    /// let transfer = bridge_transfer(reader, writer);
+    /// let (reader, writer) = transfer.finish().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn finish(self) -> io::Result<(R, W)> {
+        let result = match self.handle.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => Err(TransferError::from_panic(e.into_panic())),
+            Err(e) => return Err(io::Error::other(e)),
+        };
+        let (reader, writer) = crate::check_cancelled(&self.state, result)?;
+        Ok((reader, writer.into_inner()))
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the elapsed time since the transfer started, or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+}
@@ -0,0 +1,1687 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, prelude::*, Seek},
+    path::Path,
+    sync::{mpsc::Receiver, Arc},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use progress_streams::ProgressReader;
+
+use crate::{
+    copy::CopyOptions,
+    limiter::LimiterSlot,
+    state::{TransferState, SPEED_WINDOW},
+    BandwidthLimiter, CancelToken, Chunks, ProgressCounter, ProgressSnapshot, RetryPolicy,
+    Sequence, SizedTransfer, TimeWindow, Transfer,
+};
+#[cfg(feature = "digest")]
+use crate::{digest::Digester, DigestKind};
+
+/// Configures and starts a [`Transfer`] or [`SizedTransfer`].
+///
+/// [`Transfer::new`] and [`SizedTransfer::new`] are equivalent to using a default
+/// `TransferBuilder`.
+/// # Example
+/// ```no_run
+/// use transfer_progress::TransferBuilder;
+/// use std::fs::File;
+/// let reader = File::open("file1.txt")?;
+/// let writer = File::create("file2.txt")?;
+/// let transfer = TransferBuilder::new().build(reader, writer);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct TransferBuilder {
+    pub(crate) config: TransferConfig,
+    pub(crate) on_start: Option<Box<dyn FnOnce() + Send>>,
+    on_complete: Option<Box<dyn FnOnce() + Send>>,
+    on_error: Option<ErrorFn>,
+    on_update: Option<(Duration, UpdateFn)>,
+    on_stall: Option<Box<dyn FnMut() + Send>>,
+    /// The size of the transfer, if it's known.
+    pub(crate) size: Option<u64>,
+}
+
+type UpdateFn = Box<dyn FnMut(&ProgressSnapshot) + Send>;
+pub(crate) type ErrorFn = Box<dyn FnOnce(&io::Error) + Send>;
+
+/// The default size of the buffer used to copy data, the same as [`io::copy`][std::io::copy]'s.
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+/// The shortest time between checks for a stalled transfer.
+const STALL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// The default for [`TransferBuilder::stall_threshold`].
+const DEFAULT_STALL_THRESHOLD: Duration = Duration::from_secs(10);
+/// The default for [`TransferBuilder::eta_smoothing_time`].
+const DEFAULT_ETA_SMOOTHING_TIME: Duration = Duration::from_secs(3);
+/// The default time between samples for [`TransferBuilder::speed_history`].
+const DEFAULT_HISTORY_INTERVAL: Duration = Duration::from_secs(1);
+/// The default number of samples kept for [`TransferBuilder::speed_history`].
+const DEFAULT_HISTORY_LEN: usize = 60;
+
+/// The pieces a transfer is made from.
+pub(crate) struct Parts {
+    pub(crate) config: TransferConfig,
+    pub(crate) state: Arc<TransferState>,
+    pub(crate) options: CopyOptions,
+    /// The thread delivering updates to the [`on_update`][TransferBuilder::on_update] callback.
+    pub(crate) ticker: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for TransferBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferBuilder")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The configuration a transfer was created with, as returned by [`Transfer::config`].
+///
+/// See the corresponding [`TransferBuilder`] methods for what each option does.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TransferConfig {
+    /// Set by [`TransferBuilder::bandwidth_limiter`].
+    pub bandwidth_limiter: Option<Arc<BandwidthLimiter>>,
+    /// Set by [`TransferBuilder::track_timing`].
+    pub track_timing: bool,
+    /// Set by [`TransferBuilder::start_timer_on_first_read`].
+    pub start_timer_on_first_read: bool,
+    /// Set by [`TransferBuilder::speed_window`].
+    pub speed_window: Duration,
+    /// Set by [`TransferBuilder::speed_history`].
+    pub history_interval: Duration,
+    /// Set by [`TransferBuilder::speed_history`].
+    pub history_len: usize,
+    /// Set by [`TransferBuilder::eta_smoothing_time`].
+    pub eta_smoothing_time: Duration,
+    /// Set by [`TransferBuilder::readahead`].
+    pub readahead: bool,
+    /// Set by [`TransferBuilder::buffer_size`].
+    pub buffer_size: usize,
+    /// Set by [`TransferBuilder::schedule`].
+    pub schedule: Option<TimeWindow>,
+    /// Set by [`TransferBuilder::idle_flush`].
+    pub idle_flush: Option<Duration>,
+    /// Set by [`TransferBuilder::strict_size`].
+    pub strict_size: bool,
+    /// Set by [`TransferBuilder::limit`].
+    pub limit: Option<u64>,
+    /// Set by [`TransferBuilder::offset`].
+    pub offset: u64,
+    /// Set by [`TransferBuilder::count_written`].
+    pub count_written: bool,
+    /// Set by [`TransferBuilder::stall_threshold`].
+    pub stall_threshold: Duration,
+    /// Set by [`TransferBuilder::timeout`].
+    pub timeout: Option<Duration>,
+    /// Set by [`TransferBuilder::cancel_on_drop`].
+    pub cancel_on_drop: bool,
+    /// Set by [`TransferBuilder::create_dirs`].
+    pub create_dirs: bool,
+    /// Set by [`TransferBuilder::name`].
+    pub name: Option<String>,
+    /// Set by [`TransferBuilder::display_template`].
+    pub display_template: Option<String>,
+    /// Set by [`TransferBuilder::speed_in_bits`].
+    pub speed_in_bits: bool,
+    /// Set by [`TransferBuilder::zero_copy`].
+    pub zero_copy: bool,
+    /// Set by [`TransferBuilder::pipeline`].
+    pub pipeline: Option<usize>,
+    /// Set by [`TransferBuilder::zero_read_policy`].
+    pub zero_read_policy: ZeroReadPolicy,
+    /// Set by [`TransferBuilder::cancel_token`].
+    pub cancel_token: Option<Arc<CancelToken>>,
+    /// Set by [`TransferBuilder::max_in_flight`].
+    pub max_in_flight: Option<u64>,
+    /// Set by [`TransferBuilder::digest`].
+    #[cfg(feature = "digest")]
+    pub digest: Option<DigestKind>,
+}
+
+/// What a transfer does when a read returns no data, as set by
+/// [`TransferBuilder::zero_read_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroReadPolicy {
+    /// Treat the read as the end of the reader, as [`io::copy`][std::io::copy] does.
+    #[default]
+    Eof,
+    /// Retry the read up to this many times in a row before treating it as the end of the
+    /// reader, for readers which sometimes return no data before they reach the end.
+    Retry(u32),
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            bandwidth_limiter: None,
+            track_timing: true,
+            start_timer_on_first_read: false,
+            speed_window: SPEED_WINDOW,
+            history_interval: DEFAULT_HISTORY_INTERVAL,
+            history_len: DEFAULT_HISTORY_LEN,
+            eta_smoothing_time: DEFAULT_ETA_SMOOTHING_TIME,
+            readahead: false,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            schedule: None,
+            idle_flush: None,
+            strict_size: false,
+            limit: None,
+            offset: 0,
+            count_written: false,
+            stall_threshold: DEFAULT_STALL_THRESHOLD,
+            timeout: None,
+            cancel_on_drop: false,
+            create_dirs: false,
+            name: None,
+            display_template: None,
+            speed_in_bits: false,
+            zero_copy: false,
+            pipeline: None,
+            zero_read_policy: ZeroReadPolicy::Eof,
+            cancel_token: None,
+            max_in_flight: None,
+            #[cfg(feature = "digest")]
+            digest: None,
+        }
+    }
+}
+
+impl Default for TransferBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransferBuilder {
+    /// Creates a new `TransferBuilder` with the default configuration.
+    pub fn new() -> Self {
+        Self {
+            config: TransferConfig::default(),
+            on_start: None,
+            on_complete: None,
+            on_error: None,
+            on_update: None,
+            on_stall: None,
+            size: None,
+        }
+    }
+
+    /// Limits the speed of the transfer using a [`BandwidthLimiter`], which may be shared with
+    /// other transfers to cap their combined speed.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{BandwidthLimiter, TransferBuilder};
+    /// use std::fs::File;
+    /// use std::sync::Arc;
+    /// let limiter = Arc::new(BandwidthLimiter::new(1024 * 1024)); // 1 MiB/s
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .bandwidth_limiter(limiter)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn bandwidth_limiter(mut self, limiter: Arc<BandwidthLimiter>) -> Self {
+        self.config.bandwidth_limiter = Some(limiter);
+        self
+    }
+
+    /// Limits the speed of the transfer to `bytes_per_sec` bytes per second.
+    ///
+    /// This is shorthand for giving the transfer a [`BandwidthLimiter`] of its own, and replaces
+    /// any limiter set by [`bandwidth_limiter`][TransferBuilder::bandwidth_limiter]. The limit
+    /// can be changed while the transfer is running with [`Transfer::set_rate_limit`].
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is 0.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .rate_limit(1024 * 1024) // 1 MiB/s
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn rate_limit(self, bytes_per_sec: u64) -> Self {
+        self.bandwidth_limiter(Arc::new(BandwidthLimiter::new(bytes_per_sec)))
+    }
+
+    /// Sets whether the transfer keeps track of time (the default), so that its speed and
+    /// estimated completion time can be calculated.
+    ///
+    /// Disabling this leaves only the byte counter, for the lowest possible overhead. The transfer
+    /// then never reads the clock, and:
+    /// * [`running_time`][Transfer::running_time] always returns zero
+    /// * [`speed`][Transfer::speed] and [`speed_blended`][Transfer::speed_blended] always return 0
+    /// * [`eta`][SizedTransfer::eta] and [`eta_blended`][SizedTransfer::eta_blended] always
+    ///   return `None`
+    /// * [`time_to_first_byte`][Transfer::time_to_first_byte] always returns `None`
+    /// * [`stalled`][Transfer::stalled] always returns `false`, and
+    ///   [`on_stall`][TransferBuilder::on_stall] is never called
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .track_timing(false)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .track_timing(false)
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// assert_eq!(transfer.time_to_first_byte(), None);
+    /// assert!(!transfer.stalled());
+    /// ```
+    pub fn track_timing(mut self, track_timing: bool) -> Self {
+        self.config.track_timing = track_timing;
+        self
+    }
+
+    /// Sets whether the transfer's timer starts when its thread begins copying, rather than when
+    /// the transfer is created (the default).
+    ///
+    /// There's a short delay between a transfer being created and its thread actually being
+    /// scheduled, during which no data can be copied. Enabling this excludes that delay from
+    /// [`running_time`][Transfer::running_time], making early speed measurements more accurate.
+    /// Until the thread starts, `running_time` counts from the transfer's creation as usual, and
+    /// jumps back to zero when the thread starts.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .start_timer_on_first_read(true)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn start_timer_on_first_read(mut self, start_timer_on_first_read: bool) -> Self {
+        self.config.start_timer_on_first_read = start_timer_on_first_read;
+        self
+    }
+
+    /// Sets the length of the window over which [`Transfer::current_speed`] is measured, which is
+    /// 5 seconds by default.
+    ///
+    /// A shorter window follows changes in throughput more closely, while a longer one gives a
+    /// steadier reading. The window also affects [`Transfer::speed_blended`] and
+    /// [`SizedTransfer::eta_blended`].
+    ///
+    /// # Panics
+    /// Panics if `window` is zero.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .speed_window(Duration::from_secs(10))
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn speed_window(mut self, window: Duration) -> Self {
+        assert!(!window.is_zero(), "speed window must not be zero");
+        self.config.speed_window = window;
+        self
+    }
+
+    /// Sets how often a sample is added to the history returned by [`Transfer::speed_history`],
+    /// and how many samples it keeps, after which the oldest are dropped. By default, a sample is
+    /// taken every second, and the last 60 are kept. A `len` of 0 disables the history.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// // Keep 5 minutes of history
+    /// let transfer = TransferBuilder::new()
+    ///     .speed_history(Duration::from_secs(5), 60)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn speed_history(mut self, interval: Duration, len: usize) -> Self {
+        assert!(
+            !interval.is_zero(),
+            "speed history interval must not be zero"
+        );
+        self.config.history_interval = interval;
+        self.config.history_len = len;
+        self
+    }
+
+    /// Sets how quickly [`SizedTransfer::eta_smoothed`] follows changes in the raw
+    /// [ETA][SizedTransfer::eta], which is 3 seconds by default.
+    ///
+    /// The smoothed estimate is an exponentially weighted moving average, which closes about 63%
+    /// of the gap to the raw ETA over this much time. Longer times give a steadier countdown, but
+    /// take longer to react when the speed of the transfer really changes.
+    ///
+    /// # Panics
+    /// Panics if `time` is zero.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .eta_smoothing_time(Duration::from_secs(10))
+    ///     .build_sized(reader, writer, 1024);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn eta_smoothing_time(mut self, time: Duration) -> Self {
+        assert!(!time.is_zero(), "ETA smoothing time must not be zero");
+        self.config.eta_smoothing_time = time;
+        self
+    }
+
+    /// Sets a callback to run on the transfer's thread once it has started, right before it first
+    /// reads from the reader.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .on_start(|| println!("Transfer started"))
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn on_start<F>(mut self, on_start: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.on_start = Some(Box::new(on_start));
+        self
+    }
+
+    /// Sets a callback to run on the transfer's thread once it has completed successfully, after
+    /// its final statistics have been recorded.
+    ///
+    /// This is useful for notifying something of a [detached][Transfer::detach] transfer
+    /// finishing, without having to poll it.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// use std::sync::mpsc;
+    /// let (sender, receiver) = mpsc::channel();
+    /// TransferBuilder::new()
+    ///     .on_complete(move || sender.send("done").unwrap())
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink())
+    ///     .detach();
+    /// assert_eq!(receiver.recv(), Ok("done"));
+    /// ```
+    pub fn on_complete<F>(mut self, on_complete: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    /// Sets a callback to run on the transfer's thread if it fails, or is cancelled, with the
+    /// error [`finish`][Transfer::finish] returns, after the transfer's final statistics have been
+    /// recorded.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// use std::sync::mpsc;
+    /// let (sender, receiver) = mpsc::channel();
+    /// // A writer with room for nothing
+    /// let writer = io::Cursor::new([0; 0]);
+    /// TransferBuilder::new()
+    ///     .on_error(move |e| sender.send(e.kind()).unwrap())
+    ///     .build(io::Cursor::new(vec![0; 1024]), writer)
+    ///     .detach();
+    /// assert_eq!(receiver.recv(), Ok(io::ErrorKind::WriteZero));
+    /// ```
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where
+        F: FnOnce(&io::Error) + Send + 'static,
+    {
+        self.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// Allows the transfer to be cancelled with a [`CancelToken`], which may be shared with other
+    /// transfers to cancel them all at once.
+    ///
+    /// If the token has already been cancelled, the transfer completes immediately, failing with
+    /// [`TransferError::Cancelled`][crate::TransferError::Cancelled].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{CancelToken, TransferBuilder};
+    /// use std::fs::File;
+    /// use std::sync::Arc;
+    /// let token = Arc::new(CancelToken::new());
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .cancel_token(Arc::clone(&token))
+    ///     .build(reader, writer);
+    /// token.cancel();
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn cancel_token(mut self, token: Arc<CancelToken>) -> Self {
+        self.config.cancel_token = Some(token);
+        self
+    }
+
+    /// Sets whether to ask the OS to read ahead of the reader (disabled by default), which can
+    /// improve throughput when reading from slow disks.
+    ///
+    /// This is purely a performance hint, which has no effect on the data transferred or the
+    /// transfer's statistics. It only applies when the reader is a [`File`][std::fs::File], on its
+    /// own or wrapped in a [`Take`][std::io::Take] or [`BufReader`][std::io::BufReader], and is
+    /// only supported on Linux and Android. Otherwise, it does nothing.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .readahead(true)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// Reads may go past the region the OS was asked to read ahead, for instance with a large
+    /// [buffer size][Self::buffer_size]:
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::{self, File};
+    /// use std::io;
+    /// let path = std::env::temp_dir().join("readahead-large-buffer");
+    /// fs::write(&path, vec![0; 20 << 20])?;
+    /// let transfer = TransferBuilder::new()
+    ///     .readahead(true)
+    ///     .buffer_size(16 << 20)
+    ///     .build(File::open(&path)?, io::sink());
+    /// transfer.finish()?;
+    /// # fs::remove_file(path)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn readahead(mut self, readahead: bool) -> Self {
+        self.config.readahead = readahead;
+        self
+    }
+
+    /// Sets the size of the buffer data is copied through, which is 8 KiB by default, the same as
+    /// [`io::copy`][std::io::copy]'s.
+    ///
+    /// Each read asks the reader for at most this many bytes, so larger buffers can improve
+    /// throughput for fast readers such as large files, while smaller ones make progress updates
+    /// more frequent for slow readers such as network streams. It has no effect on transfers
+    /// whose reader provides its own chunks, such as those from
+    /// [`build_from_chunks`][TransferBuilder::build_from_chunks] or
+    /// [`build_until_delimiter`][TransferBuilder::build_until_delimiter].
+    ///
+    /// # Panics
+    /// Panics if `size` is zero.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let reader = io::Cursor::new(vec![0; 4096]);
+    /// let transfer = TransferBuilder::new()
+    ///     .buffer_size(1024)
+    ///     .build(reader, io::sink());
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "buffer size must not be zero");
+        self.config.buffer_size = size;
+        self
+    }
+
+    /// Counts `offset` bytes as already transferred before the transfer starts, for instance when
+    /// resuming a download after seeking the reader and writer past the data copied last time.
+    ///
+    /// The offset is included in [`Transfer::transferred`], and so in the progress of a
+    /// [`SizedTransfer`], whose size should be the total including the offset. It isn't counted
+    /// towards the transfer's speed, since it wasn't transferred in the time being measured, so
+    /// ETAs are based only on the bytes copied since the transfer started.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// // The first 1 KiB of 4 KiB was copied last time
+    /// let reader = io::Cursor::new(vec![0; 3 * 1024]);
+    /// let transfer = TransferBuilder::new()
+    ///     .offset(1024)
+    ///     .build_sized(reader, io::sink(), 4 * 1024);
+    /// assert!(transfer.transferred() >= 1024);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.config.offset = offset;
+        self
+    }
+
+    /// Calculates a digest of the data as it's written, which is returned by
+    /// [`Transfer::finish_with_digest`], saving reading the data again to verify it.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{DigestKind, TransferBuilder};
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .digest(DigestKind::Crc32)
+    ///     .build(io::Cursor::new(b"123456789"), io::sink());
+    /// let (_, _, digest) = transfer.finish_with_digest()?;
+    /// assert_eq!(digest, Some(vec![0xcb, 0xf4, 0x39, 0x26]));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    #[cfg(feature = "digest")]
+    pub fn digest(mut self, kind: DigestKind) -> Self {
+        self.config.digest = Some(kind);
+        self
+    }
+
+    /// Sets whether the transfer counts bytes once they've been written to the writer, rather
+    /// than as soon as they've been read from the reader (the default).
+    ///
+    /// This affects [`Transfer::transferred`] and everything based on it, such as speeds and
+    /// ETAs. Counting written bytes is more accurate when the writer is slow, since data isn't
+    /// counted while it's waiting to be written. Both counts are always available from
+    /// [`Transfer::read_bytes`] and [`Transfer::written_bytes`].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::net::TcpStream;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = TcpStream::connect("example.com:1234")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .count_written(true)
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn count_written(mut self, count_written: bool) -> Self {
+        self.config.count_written = count_written;
+        self
+    }
+
+    /// Aborts the transfer if it hasn't finished within `timeout` of starting, after which
+    /// [`finish`][Transfer::finish] returns
+    /// [`TransferError::TimedOut`][crate::TransferError::TimedOut].
+    ///
+    /// The deadline is checked before each chunk is copied, and while the transfer is paused or
+    /// waiting for its [schedule][TransferBuilder::schedule], so a single read or write which
+    /// blocks for a long time can make the transfer overrun it.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// use std::time::Duration;
+    /// // A reader which never ends
+    /// let transfer = TransferBuilder::new()
+    ///     .timeout(Duration::from_millis(10))
+    ///     .build(io::repeat(0), io::sink());
+    /// let error = transfer.finish().err().unwrap();
+    /// assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether dropping the transfer before it's complete [cancels][Transfer::cancel] it. By
+    /// default, this is disabled, and a dropped transfer carries on in the background.
+    ///
+    /// This is useful when nothing is interested in the result of a transfer once it's dropped,
+    /// for instance when the client it was serving has disconnected. It has no effect on transfers
+    /// which are [detached][Transfer::detach].
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// // A reader which never ends
+    /// let transfer = TransferBuilder::new()
+    ///     .cancel_on_drop(true)
+    ///     .build(io::repeat(0), io::sink());
+    /// let handle = transfer.progress_handle();
+    /// drop(transfer);
+    /// while !handle.is_complete() {
+    ///     std::thread::yield_now();
+    /// }
+    /// assert!(handle.is_cancelled());
+    /// ```
+    pub fn cancel_on_drop(mut self, cancel_on_drop: bool) -> Self {
+        self.config.cancel_on_drop = cancel_on_drop;
+        self
+    }
+
+    /// Sets whether [`build_path_to_path`][Self::build_path_to_path] creates any missing parent
+    /// directories of the destination before creating it (disabled by default). Other
+    /// constructors, taking a reader and writer rather than paths, aren't affected.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs;
+    /// let dir = std::env::temp_dir().join("create-dirs");
+    /// # let _ = fs::remove_dir_all(&dir);
+    /// fs::create_dir_all(&dir)?;
+    /// fs::write(dir.join("file1.txt"), "Hello, world!")?;
+    /// let to = dir.join("nested").join("deeper").join("file2.txt");
+    /// let transfer = TransferBuilder::new()
+    ///     .create_dirs(true)
+    ///     .build_path_to_path(dir.join("file1.txt"), &to)?;
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read_to_string(&to)?, "Hello, world!");
+    /// # fs::remove_dir_all(dir)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.config.create_dirs = create_dirs;
+        self
+    }
+
+    /// Sets a name for the transfer, such as the name of the file being copied, to tell it apart
+    /// from others. By default, transfers have no name.
+    ///
+    /// The name is shown at the start of the transfer's [`Display`][std::fmt::Display] output,
+    /// and is available from its [`ProgressHandle`][crate::ProgressHandle]. Unlike its
+    /// [`TransferId`][crate::TransferId], it needn't be unique.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .name("photos.tar")
+    ///     .build(io::empty(), io::sink());
+    /// assert_eq!(transfer.progress_handle().name(), Some("photos.tar"));
+    /// assert!(transfer.to_string().starts_with("photos.tar: "));
+    /// ```
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = Some(name.into());
+        self
+    }
+
+    /// Sets the layout of the transfer's [`Display`][std::fmt::Display] output, replacing the fixed
+    /// format used by default.
+    ///
+    /// Fields in braces are replaced by the transfer's statistics, and doubled braces (`{{` and
+    /// `}}`) stand for themselves. The fields are:
+    ///
+    /// | Field           | Value                                                           |
+    /// |-----------------|-----------------------------------------------------------------|
+    /// | `{transferred}` | The number of bytes transferred, like "1.2 MiB"                 |
+    /// | `{size}`        | The size of the transfer, like "4.0 MiB", or "?" if unknown     |
+    /// | `{percent}`     | The percentage transferred, like "30.0", or "?" if unknown      |
+    /// | `{speed}`       | The average speed per second, like "512.0 MiB" or "4.3 Gbit"    |
+    /// | `{eta}`         | The estimated time remaining, like "01:05", or "--:--"          |
+    /// | `{elapsed}`     | The time since the transfer started, like "00:02"               |
+    /// | `{name}`        | The transfer's [name][Self::name], or its ID if it has none     |
+    /// | `{id}`          | The transfer's [`TransferId`][crate::TransferId], like "#3"     |
+    ///
+    /// The number of decimal places of the percentage can be given like `{percent:.0}`. The
+    /// alternate flag (`{:#}`) shows sizes in SI units rather than IEC ones.
+    ///
+    /// # Panics
+    /// Panics if `template` has an unknown field, an unmatched brace, or a format on a field other
+    /// than `percent`.
+    /// # Example
+    /// ```
+    /// # #[cfg(any(feature = "bytesize", feature = "humansize"))]
+    /// # fn main() {
+    /// use transfer_progress::{SizedTransfer, TransferBuilder};
+    /// use std::io;
+    /// let transfer: SizedTransfer<_, _> = TransferBuilder::new()
+    ///     .display_template("{percent:.0}% of {size}")
+    ///     .build_sized(io::Cursor::new(vec![0; 2 << 20]), io::sink(), 2 << 20);
+    /// transfer.wait();
+    /// assert_eq!(transfer.to_string(), "100% of 2.0 MiB");
+    /// # }
+    /// # #[cfg(not(any(feature = "bytesize", feature = "humansize")))]
+    /// # fn main() {}
+    /// ```
+    pub fn display_template(mut self, template: &str) -> Self {
+        if let Err(e) = crate::template::validate(template) {
+            panic!("{}", e);
+        }
+        self.config.display_template = Some(template.to_string());
+        self
+    }
+
+    /// Sets whether the transfer's [`Display`][std::fmt::Display] output, and
+    /// [`SizedTransfer::render_line`], show its speed in bits per second, in SI units like
+    /// "12.3 Mbit/s", as network speeds are usually given, rather than bytes per second. By
+    /// default, this is disabled.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .speed_in_bits(true)
+    ///     .display_template("{speed}/s")
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// assert!(transfer.to_string().ends_with("bit/s"));
+    /// ```
+    pub fn speed_in_bits(mut self, speed_in_bits: bool) -> Self {
+        self.config.speed_in_bits = speed_in_bits;
+        self
+    }
+
+    /// Sets whether the transfer copies inside the kernel, without reading the data into a buffer,
+    /// when it can. By default, this is disabled.
+    ///
+    /// This only works on Linux, between a reader and writer which are each a
+    /// [`File`][std::fs::File], [`TcpStream`][std::net::TcpStream] or
+    /// [`UnixStream`][std::os::unix::net::UnixStream], using `copy_file_range`, `sendfile` or
+    /// `splice`, depending on what they are. Data is still copied in bounded chunks, so progress
+    /// is updated, and the transfer can be paused or cancelled, between them. Otherwise, or if the
+    /// transfer has a [bandwidth limit][Self::bandwidth_limiter], a
+    /// [maximum in flight][Self::max_in_flight], a [digest][Self::digest] or a
+    /// [limit][Self::limit], or if the kernel refuses, the data is copied through a buffer as
+    /// usual. [`Transfer::used_fast_path`] shows which happened.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::{self, File};
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("zero-copy-from"), dir.join("zero-copy-to"));
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// fs::write(&from, &data)?;
+    /// let transfer = TransferBuilder::new()
+    ///     .zero_copy(true)
+    ///     .build(File::open(&from)?, File::create(&to)?);
+    /// transfer.wait();
+    /// println!("Copied inside the kernel: {:?}", transfer.used_fast_path());
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read(&to)?, data);
+    /// # fs::remove_file(from)?;
+    /// # fs::remove_file(to)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// If the kernel refuses to write to the writer after data has been taken from the reader,
+    /// such as when splicing into a file opened for appending, that data is written through a
+    /// buffer, rather than lost:
+    /// ```
+    /// # #[cfg(target_os = "linux")]
+    /// # fn main() -> std::io::Result<()> {
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::{self, OpenOptions};
+    /// use std::io::Write;
+    /// use std::os::unix::net::UnixStream;
+    /// let path = std::env::temp_dir().join("zero-copy-append");
+    /// # let _ = fs::remove_file(&path);
+    /// let (mut sender, receiver) = UnixStream::pair()?;
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    /// let transfer = TransferBuilder::new()
+    ///     .zero_copy(true)
+    ///     .build(receiver, file);
+    /// sender.write_all(&data)?;
+    /// drop(sender);
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read(&path)?, data);
+    /// # fs::remove_file(path)?;
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # fn main() {}
+    /// ```
+    pub fn zero_copy(mut self, zero_copy: bool) -> Self {
+        self.config.zero_copy = zero_copy;
+        self
+    }
+
+    /// Reads on a thread of its own, which queues up to `depth` chunks for the transfer's thread to
+    /// write, so a slow writer doesn't hold up reading, and a slow reader doesn't hold up writing.
+    /// By default, the same thread reads and writes each chunk in turn.
+    ///
+    /// [`Transfer::queue_depth`] shows how many chunks are waiting to be written: if the queue is
+    /// usually full, the writer is the bottleneck, and if it's usually empty, the reader is. It has
+    /// no effect on transfers whose reader provides its own chunks, such as those from
+    /// [`build_from_chunks`][TransferBuilder::build_from_chunks] or
+    /// [`build_until_delimiter`][TransferBuilder::build_until_delimiter].
+    ///
+    /// # Panics
+    /// Panics if `depth` is zero.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// let transfer = TransferBuilder::new()
+    ///     .pipeline(4)
+    ///     .build(io::Cursor::new(data.clone()), Vec::new());
+    /// let (_, copy) = transfer.finish()?;
+    /// assert_eq!(copy, data);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn pipeline(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "pipeline depth must not be zero");
+        self.config.pipeline = Some(depth);
+        self
+    }
+
+    /// Stops the transfer after at most `limit` bytes.
+    ///
+    /// Like wrapping the reader in [`Take`][std::io::Take], this guarantees that no more than
+    /// `limit` bytes are ever requested from the reader, so nothing past the limit is lost from a
+    /// shared or streaming reader, but the reader is returned unwrapped. It only applies to
+    /// transfers created with [`build`][Self::build] or [`build_sized`][Self::build_sized], and is
+    /// ignored by the latter if [`strict_size`][Self::strict_size] is enabled.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, Read};
+    /// /// Records how many bytes were requested from it.
+    /// struct Recorder(usize);
+    /// impl Read for Recorder {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         self.0 += buf.len();
+    ///         buf.fill(0);
+    ///         Ok(buf.len())
+    ///     }
+    /// }
+    /// let transfer = TransferBuilder::new()
+    ///     .limit(10_000)
+    ///     .build(Recorder(0), io::sink());
+    /// let (reader, _) = transfer.finish()?;
+    /// assert_eq!(reader.0, 10_000);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.config.limit = Some(limit);
+        self
+    }
+
+    /// Sets what the transfer does when a read returns no data. By default, this is treated as the
+    /// end of the reader.
+    ///
+    /// Some poorly behaved readers occasionally return no data before they reach their end.
+    /// [`ZeroReadPolicy::Retry`] copes with these by retrying a limited number of times, so the
+    /// transfer neither ends early nor spins forever at the real end.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{TransferBuilder, ZeroReadPolicy};
+    /// use std::io::{self, Read};
+    /// /// Returns no data once, before the rest of `data`.
+    /// struct Hiccup {
+    ///     data: io::Cursor<Vec<u8>>,
+    ///     hiccuped: bool,
+    /// }
+    /// impl Read for Hiccup {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         if !self.hiccuped && self.data.position() == 4 {
+    ///             self.hiccuped = true;
+    ///             return Ok(0);
+    ///         }
+    ///         // Return a few bytes at a time, so the hiccup comes mid-transfer
+    ///         let len = buf.len().min(4);
+    ///         self.data.read(&mut buf[..len])
+    ///     }
+    /// }
+    /// let reader = Hiccup {
+    ///     data: io::Cursor::new(b"before after".to_vec()),
+    ///     hiccuped: false,
+    /// };
+    /// let transfer = TransferBuilder::new()
+    ///     .zero_read_policy(ZeroReadPolicy::Retry(3))
+    ///     .build(reader, Vec::new());
+    /// let (_, writer) = transfer.finish()?;
+    /// assert_eq!(writer, b"before after");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn zero_read_policy(mut self, policy: ZeroReadPolicy) -> Self {
+        self.config.zero_read_policy = policy;
+        self
+    }
+
+    /// Sets whether a [`SizedTransfer`] must transfer exactly its size (disabled by default).
+    ///
+    /// If enabled, the transfer fails with
+    /// [`TransferError::ShortRead`][crate::TransferError::ShortRead] if the reader runs out of
+    /// data before reaching the size, or [`TransferError::Overrun`][crate::TransferError::Overrun]
+    /// if it has more data after reaching it. In the latter case, the transfer stops at the size,
+    /// and only one extra byte is read from the reader to detect the overrun. Otherwise, the
+    /// transfer simply copies everything the reader has, whatever its size. This only applies to
+    /// transfers created with [`build_sized`][Self::build_sized].
+    ///
+    /// When converted to an [`io::Error`], as by [`SizedTransfer::finish`], a short read has the
+    /// kind [`io::ErrorKind::UnexpectedEof`], and an overrun [`io::ErrorKind::InvalidData`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::{TransferBuilder, TransferError};
+    /// use std::io;
+    /// let reader = io::Cursor::new(vec![0; 1000]);
+    /// let transfer = TransferBuilder::new()
+    ///     .strict_size(true)
+    ///     .build_sized(reader, io::sink(), 1024);
+    /// let (result, _) = transfer.finish_with_stats();
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(TransferError::ShortRead { expected: 1024, transferred: 1000 })
+    /// ));
+    ///
+    /// let reader = io::Cursor::new(vec![0; 2000]);
+    /// let transfer = TransferBuilder::new()
+    ///     .strict_size(true)
+    ///     .build_sized(reader, io::sink(), 1024);
+    /// let (result, stats) = transfer.finish_with_stats();
+    /// assert!(matches!(result, Err(TransferError::Overrun { expected: 1024 })));
+    /// assert_eq!(stats.transferred, 1024);
+    ///
+    /// let reader = io::Cursor::new(vec![0; 1000]);
+    /// let transfer = TransferBuilder::new()
+    ///     .strict_size(true)
+    ///     .build_sized(reader, io::sink(), 1024);
+    /// let err = transfer.finish().unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    /// ```
+    pub fn strict_size(mut self, strict_size: bool) -> Self {
+        self.config.strict_size = strict_size;
+        self
+    }
+
+    /// Flushes the writer whenever `bytes` bytes have been written to it without it being flushed.
+    ///
+    /// This bounds how much data can sit in a buffered writer, such as a
+    /// [`BufWriter`][std::io::BufWriter], at once. The number of flushes this causes is returned
+    /// by [`forced_flushes`][Transfer::forced_flushes].
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, BufWriter};
+    /// let reader = io::Cursor::new(vec![0; 64 * 1024]);
+    /// let writer = BufWriter::with_capacity(1024 * 1024, io::sink());
+    /// let transfer = TransferBuilder::new()
+    ///     .max_in_flight(16 * 1024)
+    ///     .build(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// assert_eq!(transfer.forced_flushes(), 4);
+    /// ```
+    pub fn max_in_flight(mut self, bytes: u64) -> Self {
+        self.config.max_in_flight = Some(bytes);
+        self
+    }
+
+    /// Flushes the writer whenever no new data has arrived from the reader for about `idle`, so
+    /// that whatever is downstream of the writer sees data promptly, rather than waiting for a
+    /// buffer to fill up.
+    ///
+    /// This is useful for interactive streams, such as piped terminal output, but costs an extra
+    /// thread and some extra flushes, trading some throughput for lower latency. Errors from these
+    /// flushes are ignored, since they will also be returned by the writer's next write.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, BufWriter};
+    /// use std::time::Duration;
+    /// let transfer = TransferBuilder::new()
+    ///     .idle_flush(Duration::from_millis(100))
+    ///     .build(io::stdin(), BufWriter::new(io::stdout()));
+    /// ```
+    pub fn idle_flush(mut self, idle: Duration) -> Self {
+        self.config.idle_flush = Some(idle);
+        self
+    }
+
+    /// Only allows the transfer to run while `window` is open, pausing it at other times of day.
+    ///
+    /// The transfer checks the time before each chunk it copies, so a chunk started just before
+    /// the window closes is still finished. Time spent paused isn't counted by
+    /// [`active_time`][Transfer::active_time], and [`eta`][SizedTransfer::eta] includes the pauses
+    /// still to come.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{TimeWindow, TransferBuilder};
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .schedule(TimeWindow::hours(1, 5)) // 01:00 to 05:00 UTC
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn schedule(mut self, window: TimeWindow) -> Self {
+        self.config.schedule = Some(window);
+        self
+    }
+
+    /// Sets a callback to receive a snapshot of the transfer's statistics every `interval`, until
+    /// it completes.
+    ///
+    /// The callback runs on a separate thread, so it doesn't slow the transfer down. It always
+    /// receives a final snapshot once the transfer is complete, before
+    /// [`finish`][Transfer::finish] returns. This replaces a loop polling the transfer, sleeping,
+    /// and printing its progress until it's complete.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .on_update(Duration::from_secs(1), |stats| {
+    ///         println!(
+    ///             "{:.0}% complete at {}B/s",
+    ///             stats.fraction_transferred().unwrap() * 100.0,
+    ///             stats.speed
+    ///         );
+    ///     })
+    ///     .build_sized(reader, writer, 1024);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    #[doc(alias = "on_progress")]
+    pub fn on_update<F>(mut self, interval: Duration, on_update: F) -> Self
+    where
+        F: FnMut(&ProgressSnapshot) + Send + 'static,
+    {
+        self.on_update = Some((interval, Box::new(on_update)));
+        self
+    }
+
+    /// Sets how long a transfer must go without transferring any bytes before it's considered to
+    /// have stalled, which is 10 seconds by default.
+    ///
+    /// See [`Transfer::stalled`] and [`on_stall`][TransferBuilder::on_stall].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .stall_threshold(Duration::from_secs(30))
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn stall_threshold(mut self, threshold: Duration) -> Self {
+        self.config.stall_threshold = threshold;
+        self
+    }
+
+    /// Sets a callback to run whenever the transfer [stalls][Transfer::stalled], going without
+    /// transferring any bytes for the [stall threshold][TransferBuilder::stall_threshold].
+    ///
+    /// The callback runs on a separate thread, once per stall: it runs again only if the
+    /// transfer makes progress and then stalls again. Stalls are only detected while timing is
+    /// [tracked][TransferBuilder::track_timing].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    /// let reader = TcpStream::connect("example.com:1234")?;
+    /// let writer = std::io::sink();
+    /// let transfer = TransferBuilder::new()
+    ///     .stall_threshold(Duration::from_secs(5))
+    ///     .on_stall(|| eprintln!("The connection seems to have hung"))
+    ///     .build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn on_stall<F>(mut self, on_stall: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.on_stall = Some(Box::new(on_stall));
+        self
+    }
+
+    /// Creates and starts a new [`Transfer`] with this configuration.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new().build(reader, writer);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build<R, W>(self, reader: R, writer: W) -> Transfer<R, W>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Transfer::with_builder(self, reader, writer)
+    }
+
+    /// Creates and starts a new [`Transfer`] which stops after reading `delimiter`, with this
+    /// configuration. See [`Transfer::until_delimiter`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::BufReader;
+    /// let reader = BufReader::new(File::open("file1.txt")?);
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new().build_until_delimiter(reader, writer, b'\n', true);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_until_delimiter<R, W>(
+        self,
+        reader: R,
+        writer: W,
+        delimiter: u8,
+        inclusive: bool,
+    ) -> Transfer<R, W>
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Transfer::spawn(self, move |state, options| {
+            crate::copy::copy_until(state, options, reader, writer, delimiter, inclusive)
+        })
+    }
+
+    /// Creates and starts a new [`Transfer`] which writes each chunk of bytes received from a
+    /// channel, with this configuration. See [`Transfer::from_chunks`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::sync::mpsc;
+    /// let (sender, receiver) = mpsc::sync_channel(16);
+    /// let writer = File::create("file.txt")?;
+    /// let transfer = TransferBuilder::new().build_from_chunks(receiver, writer);
+    /// for _ in 0..1024 {
+    ///     sender.send(vec![0; 1024]).unwrap();
+    /// }
+    /// drop(sender);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_from_chunks<W>(self, receiver: Receiver<Vec<u8>>, writer: W) -> Transfer<Chunks, W>
+    where
+        W: Write + Send + 'static,
+    {
+        Transfer::spawn(self, move |state, options| {
+            crate::copy::copy_chunks(state, options, Chunks::new(receiver), writer)
+        })
+    }
+
+    /// Creates and starts a new [`Transfer`] which copies each of a list of readers in turn, with
+    /// this configuration. See [`Transfer::sequence`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let items = vec![
+    ///     (File::open("file1.txt")?, "file1.txt"),
+    ///     (File::open("file2.txt")?, "file2.txt"),
+    /// ];
+    /// let writer = File::create("combined.txt")?;
+    /// let transfer = TransferBuilder::new().build_sequence(items, writer, |name| {
+    ///     println!("Finished {}", name);
+    /// });
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_sequence<R, N, W, F>(
+        self,
+        items: Vec<(R, N)>,
+        writer: W,
+        on_item_complete: F,
+    ) -> Transfer<Sequence<R, N>, W>
+    where
+        R: Read + Send + 'static,
+        N: Send + 'static,
+        W: Write + Send + 'static,
+        F: FnMut(&N) + Send + 'static,
+    {
+        let sequence = Sequence::new(items, on_item_complete);
+        Transfer::with_builder_and(self, sequence, writer, |state, sequence| {
+            sequence.track(Arc::clone(state));
+        })
+    }
+
+    /// Creates and starts a new [`Transfer`] from a reader that's already wrapped in a
+    /// [`ProgressReader`], with this configuration. See [`Transfer::from_progress_reader`] for
+    /// details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use progress_streams::ProgressReader;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new().build_from_progress_reader(
+    ///     |counter| ProgressReader::new(reader, move |bytes| counter.add(bytes)),
+    ///     writer,
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_from_progress_reader<F, T, C, W>(
+        self,
+        make_reader: F,
+        writer: W,
+    ) -> Transfer<ProgressReader<T, C>, W>
+    where
+        F: FnOnce(ProgressCounter) -> ProgressReader<T, C> + Send + 'static,
+        T: Read + Send + 'static,
+        C: FnMut(usize) + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Transfer::spawn(self, move |state, options| {
+            let reader = make_reader(ProgressCounter(Arc::clone(state)));
+            crate::copy::copy_counted(state, options, reader, writer)
+        })
+    }
+
+    /// Creates and starts a new [`Transfer`] which reconnects to its source if reading from it
+    /// fails, with this configuration. See [`Transfer::with_retries`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{RetryPolicy, TransferBuilder};
+    /// use std::fs::File;
+    /// use std::io::{Seek, SeekFrom};
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new().build_with_retries(
+    ///     |offset| {
+    ///         let mut reader = File::open("file1.txt")?;
+    ///         reader.seek(SeekFrom::Start(offset))?;
+    ///         Ok(reader)
+    ///     },
+    ///     writer,
+    ///     RetryPolicy::default(),
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_with_retries<F, R, W>(
+        self,
+        connect: F,
+        writer: W,
+        policy: RetryPolicy,
+    ) -> Transfer<R, W>
+    where
+        F: FnMut(u64) -> io::Result<R> + Send + 'static,
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Transfer::spawn(self, move |state, options| {
+            crate::copy::copy_retrying(state, options, connect, writer, policy)
+        })
+    }
+
+    /// Creates and starts a new [`ScopedTransfer`][crate::ScopedTransfer] on a thread in `scope`,
+    /// with this configuration.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let mut reader = File::open("file1.txt")?;
+    /// let mut writer = File::create("file2.txt")?;
+    /// std::thread::scope(|scope| {
+    ///     let transfer = TransferBuilder::new().build_scoped(scope, &mut reader, &mut writer);
+    ///     transfer.finish()
+    /// })?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_scoped<'scope, 'env, R, W>(
+        self,
+        scope: &'scope thread::Scope<'scope, 'env>,
+        reader: R,
+        writer: W,
+    ) -> crate::ScopedTransfer<'scope, R, W>
+    where
+        R: Read + Send + 'scope,
+        W: Write + Send + 'scope,
+    {
+        crate::ScopedTransfer::with_builder(self, scope, reader, writer)
+    }
+
+    /// Creates and starts a new [`SizedTransfer`] of `size` bytes with this configuration.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new().build_sized(reader, writer, 1024);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_sized<R, W>(mut self, reader: R, writer: W, size: u64) -> SizedTransfer<R, W>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        self.size = Some(size);
+        SizedTransfer::from_transfer(self.build(reader, writer))
+    }
+
+    /// Creates and starts a new [`SizedTransfer`] which scatters the data from the reader into
+    /// ranges of the writer, with this configuration. See [`SizedTransfer::scatter`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("pieces.bin")?;
+    /// let writer = File::create("file.bin")?;
+    /// let ranges = vec![(4096, 1024), (0, 1024)];
+    /// let transfer = TransferBuilder::new().build_scatter(reader, writer, ranges);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_scatter<R, W>(
+        mut self,
+        reader: R,
+        writer: W,
+        ranges: Vec<(u64, u64)>,
+    ) -> SizedTransfer<R, W>
+    where
+        R: Read + Send + 'static,
+        W: Write + Seek + Send + 'static,
+    {
+        self.size = Some(ranges.iter().map(|&(_, len)| len).sum());
+        let transfer = Transfer::spawn(self, move |state, options| {
+            crate::copy::copy_scatter(state, options, reader, writer, ranges)
+        });
+        SizedTransfer::from_transfer(transfer)
+    }
+
+    /// Creates and starts a new [`ParallelTransfer`][crate::ParallelTransfer] of `size` bytes,
+    /// split between `workers` threads, with this configuration. See
+    /// [`ParallelTransfer::new`][crate::ParallelTransfer::new] for details.
+    ///
+    /// Every worker is paused, scheduled, limited and timed out together. The workers always copy
+    /// through a buffer, without reading ahead.
+    ///
+    /// # Panics
+    /// Panics if `workers` is 0, or if a [digest][Self::digest], [idle flushing][Self::idle_flush],
+    /// a [limit][Self::limit] or [pipelining][Self::pipeline] is set, since parallel transfers
+    /// have no single stream of data for them to apply to.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::{self, File, OpenOptions};
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("build-parallel-from"), dir.join("build-parallel-to"));
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// fs::write(&from, &data)?;
+    /// File::create(&to)?;
+    /// let (from2, to2) = (from.clone(), to.clone());
+    /// let transfer = TransferBuilder::new()
+    ///     .buffer_size(1024)
+    ///     .rate_limit(10 * 1024 * 1024)
+    ///     .build_parallel(
+    ///         move || File::open(&from2),
+    ///         move || OpenOptions::new().write(true).open(&to2),
+    ///         data.len() as u64,
+    ///         4,
+    ///     );
+    /// let progress = transfer.progress_handle();
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read(&to)?, data);
+    /// // Each worker copied its quarter through a buffer of 1 KiB
+    /// assert_eq!(progress.chunks(), 4 * 25);
+    /// # fs::remove_file(from)?;
+    /// # fs::remove_file(to)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// ```should_panic
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// // Panics, since the workers can't share a pipeline
+    /// let transfer = TransferBuilder::new().pipeline(4).build_parallel(
+    ///     || Ok(io::Cursor::new(vec![0; 1024])),
+    ///     || Ok(io::Cursor::new(Vec::new())),
+    ///     1024,
+    ///     2,
+    /// );
+    /// ```
+    pub fn build_parallel<R, W, FR, FW>(
+        self,
+        open_reader: FR,
+        open_writer: FW,
+        size: u64,
+        workers: usize,
+    ) -> crate::ParallelTransfer
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+        FR: Fn() -> io::Result<R> + Send + Sync + 'static,
+        FW: Fn() -> io::Result<W> + Send + Sync + 'static,
+    {
+        crate::ParallelTransfer::with_builder(self, open_reader, open_writer, size, workers)
+    }
+
+    /// Creates and starts a new [`SizedTransfer`] copying the file at `from` to a new file at
+    /// `to`, replacing it if it already exists, with this configuration. See
+    /// [`copy_path_to_path`][crate::copy_path_to_path] for details.
+    ///
+    /// Returns an error if either file couldn't be opened, or, when
+    /// [`create_dirs`][Self::create_dirs] is enabled, the parent directories of `to` couldn't be
+    /// created.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// let transfer = TransferBuilder::new()
+    ///     .create_dirs(true)
+    ///     .build_path_to_path("file1.txt", "backup/2024/file1.txt")?;
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn build_path_to_path<P, Q>(self, from: P, to: Q) -> io::Result<SizedTransfer<File, File>>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let reader = File::open(from)?;
+        let to = to.as_ref();
+        if self.config.create_dirs {
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let writer = File::create(to)?;
+        let size = reader.metadata()?.len();
+        Ok(self.limit(size).build_sized(reader, writer, size))
+    }
+
+    /// Creates and starts a new [`DirTransfer`][crate::DirTransfer], copying the contents of the
+    /// directory at `from` into the directory at `to`, with this configuration. See
+    /// [`DirTransfer::new`][crate::DirTransfer::new] for details.
+    ///
+    /// The configuration applies to the transfer as a whole, rather than to each file: for
+    /// instance, a [timeout][Self::timeout] limits how long copying every file may take. Files are
+    /// always copied through a buffer, without reading ahead.
+    ///
+    /// Returns an error if `from` couldn't be walked, or one of kind
+    /// [`InvalidInput`][io::ErrorKind::InvalidInput], without starting the transfer, if a
+    /// [limit][Self::limit] is set, since directory transfers don't support them.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs;
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("build-dir-from"), dir.join("build-dir-to"));
+    /// # let _ = fs::remove_dir_all(&from);
+    /// # let _ = fs::remove_dir_all(&to);
+    /// fs::create_dir_all(&from)?;
+    /// fs::write(from.join("a.bin"), vec![0; 10_000])?;
+    /// fs::write(from.join("b.bin"), vec![1; 10_000])?;
+    /// let transfer = TransferBuilder::new()
+    ///     .buffer_size(1000)
+    ///     .build_dir(&from, &to)?;
+    /// let progress = transfer.progress_handle();
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read(to.join("b.bin"))?, vec![1; 10_000]);
+    /// assert_eq!(progress.chunks(), 20);
+    /// # fs::remove_dir_all(from)?;
+    /// # fs::remove_dir_all(to)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let dir = std::env::temp_dir();
+    /// let error = TransferBuilder::new()
+    ///     .limit(1024)
+    ///     .build_dir(dir.join("build-dir-limit-from"), dir.join("build-dir-limit-to"))
+    ///     .err()
+    ///     .unwrap();
+    /// assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    /// ```
+    pub fn build_dir<P, Q>(self, from: P, to: Q) -> io::Result<crate::DirTransfer>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        crate::DirTransfer::with_builder(self, from.as_ref(), to.as_ref())
+    }
+
+    /// Creates and starts a new [`BridgeTransfer`][crate::BridgeTransfer] from a synchronous
+    /// reader to an asynchronous writer, with this configuration.
+    ///
+    /// This must be called from within a Tokio runtime.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let reader = std::fs::File::open("file1.txt")?;
+    /// let writer = tokio::fs::File::create("file2.txt").await?;
+    /// let transfer = TransferBuilder::new().build_bridge(reader, writer);
+    /// let (reader, writer) = transfer.finish().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn build_bridge<R, W>(self, reader: R, writer: W) -> crate::BridgeTransfer<R, W>
+    where
+        R: Read + Send + 'static,
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        crate::BridgeTransfer::with_builder(self, reader, writer)
+    }
+
+    /// Tests if the transfer should copy inside the kernel when it can, which it can't if it needs
+    /// to see or hold back the data.
+    pub(crate) fn wants_zero_copy(&self) -> bool {
+        #[cfg(feature = "digest")]
+        let digest = self.config.digest.is_some();
+        #[cfg(not(feature = "digest"))]
+        let digest = false;
+        self.config.zero_copy
+            && self.config.bandwidth_limiter.is_none()
+            && self.config.max_in_flight.is_none()
+            && !digest
+    }
+
+    /// Returns the most bytes a transfer should copy, and whether it must copy exactly that many,
+    /// if it's limited.
+    pub(crate) fn copy_limit(&self) -> Option<(u64, bool)> {
+        match self.size.filter(|_| self.config.strict_size) {
+            // The offset was transferred before we started
+            Some(size) => Some((size.saturating_sub(self.config.offset), true)),
+            None => self.config.limit.map(|limit| (limit, false)),
+        }
+    }
+
+    /// Splits the builder into the pieces needed to start a transfer, starting the thread
+    /// delivering updates if necessary.
+    pub(crate) fn into_parts(self) -> Parts {
+        let state = Arc::new(TransferState::new(&self.config, self.size));
+        if let Some(token) = &self.config.cancel_token {
+            token.register(&state);
+        }
+        let options = CopyOptions {
+            slot: self
+                .config
+                .bandwidth_limiter
+                .clone()
+                .map(|limiter| Arc::new(LimiterSlot::new(limiter))),
+            start_timer_on_first_read: self.config.start_timer_on_first_read,
+            on_start: self.on_start,
+            on_complete: self.on_complete,
+            on_error: self.on_error,
+            readahead: None,
+            buffer_size: self.config.buffer_size,
+            schedule: self.config.schedule,
+            idle_flush: self.config.idle_flush,
+            zero_read_policy: self.config.zero_read_policy,
+            max_in_flight: self.config.max_in_flight,
+            #[cfg(feature = "digest")]
+            digester: self.config.digest.map(Digester::new),
+            #[cfg(not(feature = "digest"))]
+            digester: None,
+            timeout: self.config.timeout,
+            zero_copy: None,
+            pipeline: self.config.pipeline,
+        };
+        let ticker = self.on_update.map(|(interval, mut on_update)| {
+            let state = Arc::clone(&state);
+            thread::spawn(move || loop {
+                let complete = state.wait_complete_timeout(interval);
+                on_update(&state.snapshot());
+                if complete {
+                    break;
+                }
+            })
+        });
+        // Stalls can only be detected by the clock
+        let track_timing = self.config.track_timing;
+        if let Some(on_stall) = self.on_stall.filter(|_| track_timing) {
+            let state = Arc::clone(&state);
+            let threshold = self.config.stall_threshold;
+            thread::spawn(move || watch_for_stalls(&state, threshold, on_stall));
+        }
+        Parts {
+            config: self.config,
+            state,
+            options,
+            ticker,
+        }
+    }
+}
+
+/// Calls `on_stall` each time the transfer stalls for `threshold`, until it completes.
+fn watch_for_stalls(state: &TransferState, threshold: Duration, mut on_stall: impl FnMut()) {
+    let mut reported = false;
+    loop {
+        // Check again once the transfer could next have been idle for the threshold
+        let wait = threshold
+            .saturating_sub(state.idle_time())
+            .max(STALL_CHECK_INTERVAL);
+        if state.wait_complete_timeout(wait) {
+            return;
+        }
+        if state.is_stalled(threshold) {
+            if !reported {
+                on_stall();
+                reported = true;
+            }
+        } else {
+            reported = false;
+        }
+    }
+}
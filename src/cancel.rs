@@ -0,0 +1,139 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, Weak,
+};
+
+use crate::state::TransferState;
+
+/// Cancels any number of transfers sharing it at once.
+///
+/// Share a token between transfers by wrapping it in an [`Arc`][std::sync::Arc] and passing it to
+/// [`TransferBuilder::cancel_token`][crate::TransferBuilder::cancel_token]. Cancelling the token
+/// cancels each transfer sharing it, which stops before copying its next chunk, waking up if it's
+/// paused or waiting for its schedule, and fails with
+/// [`TransferError::Cancelled`][crate::TransferError::Cancelled]. A transfer given a token that
+/// has already been cancelled completes immediately, without transferring anything.
+/// # Example
+/// ```no_run
+/// use transfer_progress::{CancelToken, TransferBuilder};
+/// use std::fs::File;
+/// use std::sync::Arc;
+/// let token = Arc::new(CancelToken::new());
+/// let transfer1 = TransferBuilder::new()
+///     .cancel_token(Arc::clone(&token))
+///     .build(File::open("file1.txt")?, File::create("file2.txt")?);
+/// let transfer2 = TransferBuilder::new()
+///     .cancel_token(Arc::clone(&token))
+///     .build(File::open("file3.txt")?, File::create("file4.txt")?);
+/// // Stop both transfers
+/// token.cancel();
+/// assert!(transfer1.finish().is_err());
+/// assert!(transfer2.finish().is_err());
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    /// The transfers sharing this token, which are cancelled along with it.
+    transfers: Mutex<Vec<Weak<TransferState>>>,
+}
+
+impl CancelToken {
+    /// Creates a new `CancelToken`, which hasn't been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every transfer sharing this token.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{CancelToken, TransferBuilder, TransferError};
+    /// use std::io;
+    /// use std::sync::Arc;
+    /// let token = Arc::new(CancelToken::new());
+    /// token.cancel();
+    /// let transfer = TransferBuilder::new()
+    ///     .cancel_token(token)
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// let (result, stats) = transfer.finish_with_stats();
+    /// assert!(matches!(result, Err(TransferError::Cancelled)));
+    /// assert_eq!(stats.transferred, 0);
+    /// ```
+    ///
+    /// Transfers which are paused are woken up to fail straight away:
+    /// ```
+    /// use transfer_progress::{CancelToken, TransferBuilder, TransferError};
+    /// use std::io;
+    /// use std::sync::Arc;
+    /// let token = Arc::new(CancelToken::new());
+    /// let transfer = TransferBuilder::new()
+    ///     .cancel_token(Arc::clone(&token))
+    ///     .build(io::repeat(0), io::sink());
+    /// transfer.pause();
+    /// token.cancel();
+    /// let (result, _) = transfer.finish_with_stats();
+    /// assert!(matches!(result, Err(TransferError::Cancelled)));
+    /// ```
+    pub fn cancel(&self) {
+        let transfers = {
+            let mut transfers = self.transfers.lock().unwrap();
+            self.cancelled.store(true, Ordering::Release);
+            std::mem::take(&mut *transfers)
+        };
+        for state in transfers.iter().filter_map(Weak::upgrade) {
+            state.request_cancel();
+        }
+    }
+
+    /// Tests if this token has been cancelled.
+    /// # Example
+    /// ```
+    /// use transfer_progress::CancelToken;
+    /// let token = CancelToken::new();
+    /// assert!(!token.is_cancelled());
+    /// token.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Adds a transfer to be cancelled along with this token, cancelling it straight away if the
+    /// token already has been.
+    pub(crate) fn register(&self, state: &Arc<TransferState>) {
+        let mut transfers = self.transfers.lock().unwrap();
+        if self.is_cancelled() {
+            drop(transfers);
+            state.request_cancel();
+        } else {
+            // Forget the transfers which have finished with the token in the meantime
+            transfers.retain(|transfer| transfer.strong_count() > 0);
+            transfers.push(Arc::downgrade(state));
+        }
+    }
+}
+
+/// Cancels a transfer when dropped, unless it's complete, as enabled by
+/// [`TransferBuilder::cancel_on_drop`][crate::TransferBuilder::cancel_on_drop].
+pub(crate) struct CancelOnDrop(Option<Arc<TransferState>>);
+
+impl CancelOnDrop {
+    pub(crate) fn new(state: &Arc<TransferState>, enabled: bool) -> Self {
+        Self(enabled.then(|| Arc::clone(state)))
+    }
+
+    /// Stops the transfer from being cancelled when this is dropped.
+    pub(crate) fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(state) = &self.0 {
+            if !state.is_complete() {
+                state.request_cancel();
+            }
+        }
+    }
+}
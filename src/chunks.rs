@@ -0,0 +1,62 @@
+use std::{
+    io::{self, Read},
+    sync::mpsc::Receiver,
+};
+
+/// A reader over the chunks of bytes received from a channel, as used by
+/// [`Transfer::from_chunks`][crate::Transfer::from_chunks].
+///
+/// Reading returns the bytes of each chunk in turn, blocking while waiting for the next one, until
+/// the channel is closed.
+#[derive(Debug)]
+pub struct Chunks {
+    pub(crate) receiver: Receiver<Vec<u8>>,
+    /// The chunk currently being read, and how much of it has been read.
+    current: io::Cursor<Vec<u8>>,
+}
+
+impl Chunks {
+    /// Creates a new `Chunks` reading from `receiver`.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Chunks;
+    /// use std::io::Read;
+    /// use std::sync::mpsc;
+    /// let (sender, receiver) = mpsc::channel();
+    /// sender.send(b"Hello, ".to_vec()).unwrap();
+    /// sender.send(b"world!".to_vec()).unwrap();
+    /// drop(sender);
+    /// let mut text = String::new();
+    /// Chunks::new(receiver).read_to_string(&mut text)?;
+    /// assert_eq!(text, "Hello, world!");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn new(receiver: Receiver<Vec<u8>>) -> Self {
+        Self {
+            receiver,
+            current: io::Cursor::new(Vec::new()),
+        }
+    }
+
+    /// Consumes the `Chunks`, returning the channel it was reading from. Any unread data from the
+    /// chunk currently being read is lost.
+    pub fn into_inner(self) -> Receiver<Vec<u8>> {
+        self.receiver
+    }
+}
+
+impl Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            match self.receiver.recv() {
+                Ok(chunk) => self.current = io::Cursor::new(chunk),
+                // The channel is closed, so there's no more data
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
@@ -0,0 +1,969 @@
+use std::{
+    io::{self, prelude::*, SeekFrom},
+    sync::{atomic::Ordering, mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use progress_streams::ProgressReader;
+
+use crate::{
+    builder::ErrorFn,
+    chunks::Chunks,
+    digest::Digester,
+    limiter::{InnerWriter, LimitedWriter, LimiterSlot},
+    readahead::Readahead,
+    retry::{ReadTracker, RetryPolicy},
+    schedule::{self, TimeWindow},
+    state::TransferState,
+    zero_copy::ZeroCopy,
+    TransferError, ZeroReadPolicy,
+};
+
+/// The parts of a [`TransferBuilder`][crate::TransferBuilder]'s configuration used by the thread
+/// performing the transfer.
+pub(crate) struct CopyOptions {
+    pub(crate) slot: Option<Arc<LimiterSlot>>,
+    pub(crate) start_timer_on_first_read: bool,
+    pub(crate) on_start: Option<Box<dyn FnOnce() + Send>>,
+    pub(crate) on_complete: Option<Box<dyn FnOnce() + Send>>,
+    pub(crate) on_error: Option<ErrorFn>,
+    pub(crate) readahead: Option<Readahead>,
+    pub(crate) buffer_size: usize,
+    pub(crate) schedule: Option<TimeWindow>,
+    pub(crate) idle_flush: Option<Duration>,
+    pub(crate) zero_read_policy: ZeroReadPolicy,
+    pub(crate) max_in_flight: Option<u64>,
+    pub(crate) digester: Option<Digester>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) zero_copy: Option<ZeroCopy>,
+    pub(crate) pipeline: Option<usize>,
+}
+
+/// The shortest time between two progress events being emitted with `tracing`.
+#[cfg(feature = "tracing")]
+const TRACE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Everything the copy loops need besides the reader and writer.
+pub(crate) struct CopyContext<'a> {
+    state: &'a TransferState,
+    readahead: Option<Readahead>,
+    /// The size of the buffer data is copied through.
+    buffer_size: usize,
+    schedule: Option<TimeWindow>,
+    zero_read_policy: ZeroReadPolicy,
+    /// The number of reads in a row which have returned no data.
+    zero_reads: u32,
+    digester: Option<Digester>,
+    /// When the transfer must finish by, if it has a timeout.
+    deadline: Option<Instant>,
+    /// Copies inside the kernel, if the transfer can.
+    zero_copy: Option<ZeroCopy>,
+    /// How many chunks may be queued between the reading and writing threads, if the transfer
+    /// reads on a thread of its own.
+    pipeline: Option<usize>,
+    /// When the last progress event was emitted with `tracing`.
+    #[cfg(feature = "tracing")]
+    last_traced: Instant,
+}
+
+impl<'a> CopyContext<'a> {
+    /// Returns a context for copying another part of the same transfer, or reading for it, on
+    /// another thread, with the same settings. It starts out without copying inside the kernel,
+    /// reading ahead, or calculating a digest.
+    fn fork(&self) -> CopyContext<'a> {
+        CopyContext {
+            state: self.state,
+            readahead: None,
+            buffer_size: self.buffer_size,
+            schedule: self.schedule,
+            zero_read_policy: self.zero_read_policy,
+            zero_reads: 0,
+            digester: None,
+            deadline: self.deadline,
+            zero_copy: None,
+            pipeline: self.pipeline,
+            #[cfg(feature = "tracing")]
+            last_traced: self.last_traced,
+        }
+    }
+
+    /// Blocks while the transfer is paused, and until its schedule allows it to run, then checks
+    /// it hasn't been cancelled or timed out. Called before copying each chunk.
+    fn before_chunk(&self) -> Result<(), TransferError> {
+        self.wait_until(None, || !self.state.is_paused());
+        self.wait_for_schedule();
+        if self.is_cancelled() {
+            Err(TransferError::Cancelled)
+        } else if self.is_timed_out() {
+            Err(TransferError::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Tests if the transfer has passed its deadline.
+    fn is_timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Tests if the transfer should stop waiting, because it has been cancelled or timed out.
+    fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.is_timed_out()
+    }
+
+    /// Tests if the transfer has been cancelled, either by itself or by its
+    /// [`CancelToken`][crate::CancelToken].
+    fn is_cancelled(&self) -> bool {
+        self.state.cancel_requested.load(Ordering::Acquire)
+    }
+
+    /// Blocks until `done` returns `true`, or `until` passes if it's given, returning early if the
+    /// transfer is cancelled or times out.
+    fn wait_until(&self, until: Option<Instant>, done: impl Fn() -> bool) {
+        let until = match (until, self.deadline) {
+            (Some(until), Some(deadline)) => Some(until.min(deadline)),
+            (until, deadline) => until.or(deadline),
+        };
+        self.state
+            .wait_until(until, || done() || self.should_stop());
+    }
+
+    /// Blocks until the transfer's schedule allows it to run, or it is cancelled or times out.
+    fn wait_for_schedule(&self) {
+        let schedule = match &self.schedule {
+            Some(schedule) => schedule,
+            None => return,
+        };
+        let parked_at = Instant::now();
+        while !self.should_stop() {
+            let wait = schedule.until_open(schedule::time_of_day());
+            if wait.is_zero() {
+                break;
+            }
+            self.wait_until(Some(Instant::now() + wait), || false);
+        }
+        self.state.add_parked(parked_at.elapsed());
+    }
+
+    /// Blocks for `backoff` before retrying, then checks the transfer hasn't been cancelled or
+    /// timed out in the meantime, as [`before_chunk`][Self::before_chunk] does.
+    fn back_off(&self, backoff: Duration) -> Result<(), TransferError> {
+        self.wait_until(Some(Instant::now() + backoff), || false);
+        self.before_chunk()
+    }
+
+    /// Records that a chunk of `len` bytes has been read.
+    fn chunk_read(&mut self, len: usize) {
+        self.zero_reads = 0;
+        self.state.max_chunk.fetch_max(len as u64, Ordering::AcqRel);
+        if let Some(readahead) = &mut self.readahead {
+            readahead.advance(len as u64);
+        }
+    }
+
+    /// Called when a read returns no data, to decide whether the reader has really reached its
+    /// end, according to the [`ZeroReadPolicy`].
+    fn is_eof(&mut self) -> bool {
+        match self.zero_read_policy {
+            ZeroReadPolicy::Retry(retries) if self.zero_reads < retries => {
+                self.zero_reads += 1;
+                thread::yield_now();
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Adds `data`, which has just been written, to the transfer's digest, if it has one.
+    fn digest(&mut self, data: &[u8]) {
+        if let Some(digester) = &mut self.digester {
+            digester.update(data);
+        }
+    }
+
+    /// Records that a chunk has been read and written.
+    fn chunk_done(&mut self) {
+        self.state.chunks.fetch_add(1, Ordering::Release);
+        #[cfg(feature = "tracing")]
+        if self.last_traced.elapsed() >= TRACE_INTERVAL {
+            self.last_traced = Instant::now();
+            tracing::trace!(
+                transferred = self.state.transferred(),
+                speed = self.state.speed().round() as u64,
+                "transfer progress"
+            );
+        }
+    }
+}
+
+/// Copies everything from `reader` to `writer`, keeping `state` up to date, then marks the
+/// transfer as complete.
+pub(crate) fn copy<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: R,
+    writer: W,
+) -> Result<(R, W), TransferError>
+where
+    R: Read + Send,
+    W: Write + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| {
+        if let Some(mut zero_copy) = ctx.zero_copy.take() {
+            if copy_zero(ctx, &mut zero_copy, writer)? {
+                return Ok(());
+            }
+        }
+        let state = ctx.state;
+        let mut reader = ProgressReader::new(reader, |bytes| {
+            state.add_read(bytes as u64);
+        });
+        copy_loop(ctx, &mut reader, writer)
+    })
+}
+
+/// Copies everything inside the kernel with `zero_copy`, keeping `state` up to date. Returns
+/// `false` if the kernel can't copy between the reader and writer, in which case the rest should
+/// be copied through a buffer instead. Anything already taken from the reader by then is written
+/// to `writer` first, so nothing is lost.
+fn copy_zero(
+    ctx: &mut CopyContext,
+    zero_copy: &mut ZeroCopy,
+    writer: &mut impl Write,
+) -> Result<bool, TransferError> {
+    let mut copied = false;
+    loop {
+        ctx.before_chunk()?;
+        let len = match ctx.state.time_read(|| zero_copy.copy_chunk()) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if !copied && ZeroCopy::is_unsupported(&e) => {
+                let stranded = zero_copy.take_stranded()?;
+                if !stranded.is_empty() {
+                    ctx.chunk_read(stranded.len());
+                    ctx.state.add_read(stranded.len() as u64);
+                    writer.write_all(&stranded)?;
+                    ctx.chunk_done();
+                }
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        copied = true;
+        ctx.chunk_read(len);
+        ctx.state.add_read(len as u64);
+        ctx.state.add_written(len as u64);
+        ctx.chunk_done();
+    }
+    ctx.state.used_fast_path.store(true, Ordering::Release);
+    Ok(true)
+}
+
+/// Copies at most `limit` bytes from `reader` to `writer`, keeping `state` up to date, then marks
+/// the transfer as complete. No more than `limit` bytes are ever requested from `reader`.
+///
+/// If `exact` is `true`, fails if `reader` has fewer than `limit` bytes, or more, in which case
+/// the first extra byte is consumed from `reader`, but not written.
+pub(crate) fn copy_limited<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: R,
+    writer: W,
+    limit: u64,
+    exact: bool,
+) -> Result<(R, W), TransferError>
+where
+    R: Read + Send,
+    W: Write + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| {
+        let state = ctx.state;
+        let mut limited = LimitReader {
+            inner: reader,
+            remaining: limit,
+        };
+        copy_loop(
+            ctx,
+            &mut ProgressReader::new(&mut limited, |bytes| {
+                state.add_read(bytes as u64);
+            }),
+            writer,
+        )?;
+        if !exact {
+            Ok(())
+        } else if limited.remaining > 0 {
+            Err(TransferError::ShortRead {
+                expected: limit,
+                transferred: limit - limited.remaining,
+            })
+        } else if has_more(limited.inner)? {
+            Err(TransferError::Overrun { expected: limit })
+        } else {
+            Ok(())
+        }
+    })
+}
+
+/// Reads at most `remaining` bytes from `inner`, never asking it for any more.
+struct LimitReader<'a, R> {
+    inner: &'a mut R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for LimitReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let len = (buf.len() as u64).min(self.remaining) as usize;
+        let read = self.inner.read(&mut buf[..len])?;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Tests if `reader` has any more data, consuming at most one byte of it.
+fn has_more<R: Read>(reader: &mut R) -> io::Result<bool> {
+    let mut probe = [0];
+    loop {
+        match reader.read(&mut probe) {
+            Ok(read) => return Ok(read > 0),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes each chunk received by `reader` to `writer` as it arrives, keeping `state` up to date,
+/// until the channel is closed, then marks the transfer as complete.
+pub(crate) fn copy_chunks<W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: Chunks,
+    writer: W,
+) -> Result<(Chunks, W), TransferError>
+where
+    W: Write + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| loop {
+        ctx.before_chunk()?;
+        let chunk = match ctx.state.time_read(|| reader.receiver.recv()) {
+            Ok(chunk) => chunk,
+            // The channel is closed, so there's no more data
+            Err(_) => return Ok(()),
+        };
+        if chunk.is_empty() {
+            continue;
+        }
+        ctx.chunk_read(chunk.len());
+        ctx.state.add_read(chunk.len() as u64);
+        writer.write_all(&chunk)?;
+        ctx.digest(&chunk);
+        writer.flush_if_full()?;
+        ctx.chunk_done();
+    })
+}
+
+/// Copies everything from `reader`, which counts the bytes it reads itself, to `writer`, then
+/// marks the transfer as complete.
+pub(crate) fn copy_counted<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: R,
+    writer: W,
+) -> Result<(R, W), TransferError>
+where
+    R: Read + Send,
+    W: Write + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| {
+        copy_loop(ctx, reader, writer)
+    })
+}
+
+/// Copies from `reader` to `writer` until `delimiter` is read, keeping `state` up to date, then
+/// marks the transfer as complete. The delimiter is only written if `inclusive` is `true`.
+pub(crate) fn copy_until<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: R,
+    writer: W,
+    delimiter: u8,
+    inclusive: bool,
+) -> Result<(R, W), TransferError>
+where
+    R: BufRead,
+    W: Write + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| loop {
+        ctx.before_chunk()?;
+        let (found, consumed, written) = {
+            let buf = match ctx.state.time_read(|| reader.fill_buf()) {
+                Ok([]) if ctx.is_eof() => return Ok(()),
+                Ok([]) => continue,
+                Ok(buf) => buf,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let (found, consumed, written) = match buf.iter().position(|&b| b == delimiter) {
+                Some(pos) => (true, pos + 1, if inclusive { pos + 1 } else { pos }),
+                None => (false, buf.len(), buf.len()),
+            };
+            writer.write_all(&buf[..written])?;
+            ctx.digest(&buf[..written]);
+            (found, consumed, written)
+        };
+        writer.flush_if_full()?;
+        reader.consume(consumed);
+        ctx.chunk_read(consumed);
+        // The delimiter is only counted if it's written
+        ctx.state.add_read(written as u64);
+        ctx.chunk_done();
+        if found {
+            return Ok(());
+        }
+    })
+}
+
+/// Copies from `reader` into each of `ranges` (as `(offset, length)` pairs) of `writer` in turn,
+/// keeping `state` up to date, then marks the transfer as complete.
+pub(crate) fn copy_scatter<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    reader: R,
+    writer: W,
+    ranges: Vec<(u64, u64)>,
+) -> Result<(R, W), TransferError>
+where
+    R: Read + Send,
+    W: Write + Seek + Send,
+{
+    run(state, options, reader, writer, |ctx, reader, writer| {
+        for (offset, len) in ranges {
+            writer.seek(SeekFrom::Start(offset))?;
+            let state = ctx.state;
+            let mut range_reader = ProgressReader::new(reader.take(len), |bytes| {
+                state.add_read(bytes as u64);
+            });
+            copy_loop(ctx, &mut range_reader, writer)?;
+            check_range_filled(range_reader.into_inner().limit(), len, offset)?;
+        }
+        Ok(())
+    })
+}
+
+/// Copies each of `ranges` (as `(offset, length)` pairs) from a reader made by `open_reader` to
+/// the same place in a writer made by `open_writer`, keeping `state` up to date, then marks the
+/// transfer as complete.
+///
+/// Every range is copied at the same time, on a thread of its own, which opens its own reader and
+/// writer. If any of them fails, the others are stopped, and the first error is returned.
+pub(crate) fn copy_parallel<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    open_reader: impl Fn() -> io::Result<R> + Sync,
+    open_writer: impl Fn() -> io::Result<W> + Sync,
+    ranges: Vec<(u64, u64)>,
+) -> Result<(), TransferError>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let (open_reader, open_writer) = (&open_reader, &open_writer);
+    // Each worker has a reader and writer of its own, so there are none to hand to `run`
+    run(state, options, (), io::sink(), |ctx, (), sink| {
+        thread::scope(|scope| {
+            let workers: Vec<_> = ranges
+                .into_iter()
+                .map(|(offset, len)| {
+                    let mut ctx = ctx.fork();
+                    // Every worker draws from the transfer's share of the bandwidth limit
+                    let (slot, max_in_flight) = (sink.slot.clone(), sink.max_in_flight);
+                    scope.spawn(move || {
+                        let res = (|| {
+                            let mut reader = open_reader()?;
+                            let mut writer = open_writer()?;
+                            reader.seek(SeekFrom::Start(offset))?;
+                            writer.seek(SeekFrom::Start(offset))?;
+                            let mut limited = LimitedWriter {
+                                inner: InnerWriter::Exclusive(&mut writer),
+                                slot,
+                                max_in_flight,
+                                unflushed: 0,
+                                state,
+                            };
+                            let mut range_reader = ProgressReader::new(reader.take(len), |bytes| {
+                                state.add_read(bytes as u64);
+                            });
+                            copy_buffered(&mut ctx, &mut range_reader, &mut limited)?;
+                            limited.flush()?;
+                            check_range_filled(range_reader.into_inner().limit(), len, offset)
+                        })();
+                        if res.is_err() {
+                            // Stop the other workers, since the transfer has failed
+                            state.request_cancel();
+                        }
+                        res
+                    })
+                })
+                .collect();
+            let mut res = Ok(());
+            for worker in workers {
+                let worker_res = worker
+                    .join()
+                    .unwrap_or_else(|payload| Err(TransferError::from_panic(payload)));
+                // Prefer any other error to the cancellation it caused
+                if let (Ok(()) | Err(TransferError::Cancelled), Err(e)) = (&res, worker_res) {
+                    res = Err(e);
+                }
+            }
+            res
+        })
+    })
+    .map(|_| ())
+}
+
+/// Copies from each reader to each writer returned by `open` in turn, keeping `state` up to date,
+/// then marks the transfer as complete.
+///
+/// `open` is called with `0`, `1`, `2` and so on, until it returns `None`, to open the next reader
+/// and writer once the last ones are done with. Each writer is flushed after it's written to.
+pub(crate) fn copy_each<R, W>(
+    state: &TransferState,
+    options: CopyOptions,
+    mut open: impl FnMut(usize) -> Result<Option<(R, W)>, TransferError>,
+) -> Result<(), TransferError>
+where
+    R: Read + Send,
+    W: Write,
+{
+    // The readers and writers come and go, so there are none to hand to `run`
+    run(state, options, (), io::sink(), |ctx, (), sink| {
+        let mut index = 0;
+        while let Some((reader, mut writer)) = open(index)? {
+            let mut limited = LimitedWriter {
+                inner: InnerWriter::Exclusive(&mut writer),
+                slot: sink.slot.clone(),
+                max_in_flight: sink.max_in_flight,
+                unflushed: 0,
+                state,
+            };
+            let mut reader = ProgressReader::new(reader, |bytes| {
+                state.add_read(bytes as u64);
+            });
+            copy_loop(ctx, &mut reader, &mut limited)?;
+            limited.flush()?;
+            index += 1;
+        }
+        Ok(())
+    })
+    .map(|_| ())
+}
+
+/// Fails if a range of `len` bytes at `offset` was left with `unfilled` bytes uncopied, because
+/// the reader ended first.
+fn check_range_filled(unfilled: u64, len: u64, offset: u64) -> Result<(), TransferError> {
+    if unfilled == 0 {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+            "reader ended {} bytes short of filling the range of {} bytes at offset {}",
+            unfilled, len, offset
+        ),
+    )
+    .into())
+}
+
+/// Copies everything from the readers made by `connect` to `writer`, keeping `state` up to date,
+/// then marks the transfer as complete.
+///
+/// `connect` is called with the number of bytes read so far to make the first reader, and again
+/// to make a new one, carrying on from the same point, whenever reading fails, or `connect`
+/// itself does, as allowed by `policy`. If the transfer is cancelled while there's no reader, it
+/// fails with [`TransferError::Cancelled`], since there's no reader to hand back.
+pub(crate) fn copy_retrying<R, W, F>(
+    state: &TransferState,
+    options: CopyOptions,
+    mut connect: F,
+    writer: W,
+    policy: RetryPolicy,
+) -> Result<(R, W), TransferError>
+where
+    R: Read + Send,
+    W: Write + Send,
+    F: FnMut(u64) -> io::Result<R>,
+{
+    let (reader, writer) = run(state, options, None, writer, |ctx, reader, writer| {
+        let mut position = 0;
+        let mut retries = 0;
+        loop {
+            let error = match reader {
+                Some(reader) => {
+                    let state = ctx.state;
+                    let mut tracker = ReadTracker::new(reader);
+                    let result = copy_loop(
+                        ctx,
+                        &mut ProgressReader::new(&mut tracker, |bytes| {
+                            state.add_read(bytes as u64);
+                        }),
+                        writer,
+                    );
+                    position += tracker.read;
+                    if tracker.read > 0 {
+                        retries = 0;
+                    }
+                    match result {
+                        // Only failures of the reader are retried
+                        Err(TransferError::Io(e)) if tracker.failed => e,
+                        result => return result,
+                    }
+                }
+                None => match connect(position) {
+                    Ok(new_reader) => {
+                        *reader = Some(new_reader);
+                        continue;
+                    }
+                    Err(e) => e,
+                },
+            };
+            if retries >= policy.max_retries() {
+                return Err(error.into());
+            }
+            *reader = None;
+            ctx.back_off(policy.backoff(retries))?;
+            retries += 1;
+        }
+    })?;
+    match reader {
+        Some(reader) => Ok((reader, writer)),
+        None => Err(TransferError::Cancelled),
+    }
+}
+
+/// Does everything common to all kinds of transfer around `copy_fn`, which does the actual
+/// copying.
+fn run<R, W, F>(
+    state: &TransferState,
+    options: CopyOptions,
+    mut reader: R,
+    mut writer: W,
+    copy_fn: F,
+) -> Result<(R, W), TransferError>
+where
+    W: Write + Send,
+    F: FnOnce(&mut CopyContext, &mut R, &mut LimitedWriter<W>) -> Result<(), TransferError>,
+{
+    let CopyOptions {
+        slot,
+        start_timer_on_first_read,
+        on_start,
+        on_complete,
+        on_error,
+        readahead,
+        buffer_size,
+        schedule,
+        idle_flush,
+        zero_read_policy,
+        max_in_flight,
+        digester,
+        timeout,
+        zero_copy,
+        pipeline,
+    } = options;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    if let Some(on_start) = on_start {
+        on_start();
+    }
+    if start_timer_on_first_read {
+        state.restart_timer();
+    }
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "transfer",
+        id = state.id.get(),
+        name = state.name.as_deref(),
+        size = state.size()
+    );
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+    let copy = |inner: InnerWriter<'_, W>| {
+        let mut limited = LimitedWriter {
+            inner,
+            slot,
+            max_in_flight,
+            unflushed: 0,
+            state,
+        };
+        // We need to store the result and bubble it later so we can set the complete flag.
+        let mut ctx = CopyContext {
+            state,
+            readahead,
+            buffer_size,
+            schedule,
+            zero_read_policy,
+            zero_reads: 0,
+            digester,
+            deadline,
+            zero_copy,
+            pipeline,
+            #[cfg(feature = "tracing")]
+            last_traced: Instant::now(),
+        };
+        let res = copy_fn(&mut ctx, &mut reader, &mut limited);
+        // Release our share of the bandwidth limit as soon as we're done with it
+        drop(limited);
+        if let (Ok(()), Some(digester)) = (&res, ctx.digester) {
+            state.set_digest(digester.finish());
+        }
+        res
+    };
+    // Only put the writer behind a lock if there's another thread to share it with
+    let (res, writer) = match idle_flush {
+        Some(idle) => {
+            let writer = Mutex::new(writer);
+            let stop = Stop::default();
+            let res = thread::scope(|scope| {
+                scope.spawn(|| flush_when_idle(state, &writer, idle, &stop));
+                // Make sure the flushing thread stops even if we panic, or the scope would never
+                // end
+                let _stop = StopOnDrop(&stop);
+                copy(InnerWriter::Shared(&writer))
+            });
+            (res, writer.into_inner().unwrap())
+        }
+        None => (copy(InnerWriter::Exclusive(&mut writer)), writer),
+    };
+    let failure = res.as_ref().err().map(TransferError::duplicate);
+    let res = match res {
+        // Hand back the reader and writer, so whoever cancelled the transfer can clean up
+        Err(TransferError::Cancelled) => {
+            state.cancelled.store(true, Ordering::Release);
+            state.failed.store(true, Ordering::Release);
+            Ok(())
+        }
+        Err(e) => {
+            state.set_failed(&e);
+            Err(e)
+        }
+        Ok(()) => Ok(()),
+    };
+    state.set_complete();
+    #[cfg(feature = "tracing")]
+    trace_completion(state, &res);
+    match (failure, on_complete, on_error) {
+        (None, Some(on_complete), _) => on_complete(),
+        (Some(e), _, Some(on_error)) => on_error(&e.into()),
+        _ => {}
+    }
+    res.map(|_| (reader, writer))
+}
+
+/// Emits an event with `tracing` describing how the transfer ended, with its final statistics.
+#[cfg(feature = "tracing")]
+fn trace_completion(state: &TransferState, res: &Result<(), TransferError>) {
+    let transferred = state.transferred();
+    let elapsed = state.running_time();
+    let speed = state.speed().round() as u64;
+    match res {
+        Err(e) => tracing::warn!(transferred, ?elapsed, speed, error = %e, "transfer failed"),
+        Ok(()) if state.cancelled.load(Ordering::Acquire) => {
+            tracing::info!(transferred, ?elapsed, speed, "transfer cancelled");
+        }
+        Ok(()) => tracing::info!(transferred, ?elapsed, speed, "transfer complete"),
+    }
+}
+
+/// Tells the thread flushing the writer when the transfer is idle to stop.
+#[derive(Default)]
+struct Stop {
+    stopped: Mutex<bool>,
+    changed: Condvar,
+}
+
+struct StopOnDrop<'a>(&'a Stop);
+
+impl Drop for StopOnDrop<'_> {
+    fn drop(&mut self) {
+        *self.0.stopped.lock().unwrap() = true;
+        self.0.changed.notify_all();
+    }
+}
+
+/// Flushes `writer` whenever no bytes have been transferred for `idle` since the last check,
+/// until told to `stop`.
+fn flush_when_idle<W: Write>(
+    state: &TransferState,
+    writer: &Mutex<W>,
+    idle: Duration,
+    stop: &Stop,
+) {
+    let mut last_seen = state.transferred();
+    let mut flushed = last_seen;
+    let mut stopped = stop.stopped.lock().unwrap();
+    loop {
+        stopped = stop
+            .changed
+            .wait_timeout_while(stopped, idle, |stopped| !*stopped)
+            .unwrap()
+            .0;
+        if *stopped {
+            return;
+        }
+        let transferred = state.transferred();
+        if transferred == last_seen && transferred != flushed {
+            // Any error will be returned by the next write too, so it can be reported from there
+            if let Ok(mut writer) = writer.lock() {
+                let _ = writer.flush();
+            }
+            flushed = transferred;
+        }
+        last_seen = transferred;
+    }
+}
+
+/// Copies everything from `reader` to `writer`, through a thread of its own reading from `reader`
+/// if the transfer is [pipelined][crate::TransferBuilder::pipeline].
+fn copy_loop<R, W>(
+    ctx: &mut CopyContext,
+    reader: &mut R,
+    writer: &mut LimitedWriter<W>,
+) -> Result<(), TransferError>
+where
+    R: Read + Send,
+    W: Write,
+{
+    match ctx.pipeline {
+        Some(depth) => copy_pipelined(ctx, reader, writer, depth),
+        None => copy_buffered(ctx, reader, writer),
+    }
+}
+
+fn copy_buffered<R, W>(
+    ctx: &mut CopyContext,
+    reader: &mut R,
+    writer: &mut LimitedWriter<W>,
+) -> Result<(), TransferError>
+where
+    R: Read,
+    W: Write,
+{
+    let mut buf = vec![0; ctx.buffer_size];
+    loop {
+        ctx.before_chunk()?;
+        let len = match ctx.state.time_read(|| reader.read(&mut buf)) {
+            Ok(0) if ctx.is_eof() => return Ok(()),
+            Ok(0) => continue,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        ctx.chunk_read(len);
+        writer.write_all(&buf[..len])?;
+        ctx.digest(&buf[..len]);
+        writer.flush_if_full()?;
+        ctx.chunk_done();
+    }
+}
+
+/// Copies everything from `reader` to `writer`, reading on another thread, which queues up to
+/// `depth` chunks for this one to write.
+///
+/// An empty chunk marks the end of the reader. Written buffers are sent back to the reading thread
+/// to be reused.
+fn copy_pipelined<R, W>(
+    ctx: &mut CopyContext,
+    reader: &mut R,
+    writer: &mut LimitedWriter<W>,
+    depth: usize,
+) -> Result<(), TransferError>
+where
+    R: Read + Send,
+    W: Write,
+{
+    let state = ctx.state;
+    let mut reading = ctx.fork();
+    // Hints about what will be read next are up to the thread doing the reading
+    reading.readahead = ctx.readahead.take();
+    // The reading thread holds one more chunk while it waits to send it
+    let (full_sender, full_receiver) = mpsc::sync_channel(depth - 1);
+    let (empty_sender, empty_receiver) = mpsc::channel();
+    let res = thread::scope(|scope| {
+        let reading = &mut reading;
+        scope.spawn(move || read_ahead(reading, reader, &full_sender, &empty_receiver));
+        let res = (|| loop {
+            ctx.before_chunk()?;
+            let buf: Vec<u8> = match ctx.state.time_read(|| full_receiver.recv()) {
+                Ok(chunk) => {
+                    state.pop_queued(chunk.as_ref().map_or(0, |buf| buf.len() as u64));
+                    chunk?
+                }
+                // The reading thread has stopped, having sent the end of the reader or an error
+                Err(_) => return Ok(()),
+            };
+            if buf.is_empty() {
+                return Ok(());
+            }
+            ctx.chunk_read(buf.len());
+            writer.write_all(&buf)?;
+            ctx.digest(&buf);
+            writer.flush_if_full()?;
+            ctx.chunk_done();
+            // The reading thread may already have stopped, in which case the buffer isn't needed
+            let _ = empty_sender.send(buf);
+        })();
+        // Make the reading thread stop if it's still going, or the scope would never end
+        drop(full_receiver);
+        res
+    });
+    state.clear_queued();
+    ctx.readahead = reading.readahead.take();
+    res
+}
+
+/// Reads chunks from `reader` and sends them to the writing thread through `full`, reusing the
+/// buffers it sends back through `empty`, until the end of the reader, an error, the transfer
+/// being cancelled or timing out, or the writing thread stopping. Doesn't read while the transfer
+/// is paused.
+fn read_ahead<R: Read>(
+    ctx: &mut CopyContext,
+    reader: &mut R,
+    full: &mpsc::SyncSender<io::Result<Vec<u8>>>,
+    empty: &mpsc::Receiver<Vec<u8>>,
+) {
+    let state = ctx.state;
+    loop {
+        ctx.wait_until(None, || !state.is_paused());
+        // The writing thread reports the cancellation or timeout
+        if ctx.should_stop() {
+            return;
+        }
+        let mut buf = empty.try_recv().unwrap_or_default();
+        buf.resize(ctx.buffer_size, 0);
+        let chunk = match reader.read(&mut buf) {
+            Ok(0) if ctx.is_eof() => Ok(Vec::new()),
+            Ok(0) => continue,
+            Ok(len) => {
+                ctx.zero_reads = 0;
+                if let Some(readahead) = &mut ctx.readahead {
+                    readahead.advance(len as u64);
+                }
+                buf.truncate(len);
+                Ok(buf)
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => Err(e),
+        };
+        let len = chunk.as_ref().map_or(0, |buf| buf.len() as u64);
+        let last = len == 0;
+        // Count the chunk before sending it, so the writing thread never counts it first
+        state.push_queued(len);
+        if full.send(chunk).is_err() || last {
+            return;
+        }
+    }
+}
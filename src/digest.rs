@@ -0,0 +1,60 @@
+#[cfg(feature = "digest")]
+use sha2::Digest as _;
+
+/// A checksum or hash which can be calculated over the data as it's transferred, as set by
+/// [`TransferBuilder::digest`][crate::TransferBuilder::digest].
+#[cfg(feature = "digest")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DigestKind {
+    /// A CRC-32 (IEEE) checksum, as 4 big-endian bytes.
+    Crc32,
+    /// A SHA-256 hash, as 32 bytes.
+    Sha256,
+}
+
+/// Calculates a digest over the data transferred.
+#[cfg(feature = "digest")]
+pub(crate) enum Digester {
+    Crc32(crc32fast::Hasher),
+    Sha256(sha2::Sha256),
+}
+
+/// Digests can't be calculated without the `digest` feature.
+#[cfg(not(feature = "digest"))]
+pub(crate) enum Digester {}
+
+#[cfg(feature = "digest")]
+impl Digester {
+    pub(crate) fn new(kind: DigestKind) -> Self {
+        match kind {
+            DigestKind::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+            DigestKind::Sha256 => Self::Sha256(sha2::Sha256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(not(feature = "digest"))]
+impl Digester {
+    pub(crate) fn update(&mut self, _data: &[u8]) {
+        match *self {}
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {}
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    io::{self, prelude::*},
+    net::{Shutdown, TcpStream},
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    state::{CompleteOnPanic, TransferState},
+    ProgressHandle, TransferConfig, TransferError,
+};
+
+/// Monitors the progress of copying data in both directions between two [`TcpStream`]s at once,
+/// as a proxy does.
+///
+/// Each direction is copied by a thread of its own, and has its own statistics, available through
+/// [`a_to_b`][DuplexTransfer::a_to_b] and [`b_to_a`][DuplexTransfer::b_to_a], while the methods
+/// of the `DuplexTransfer` itself report both directions combined. When one side stops sending,
+/// the write half of the other side is shut down, so that the end of the stream is passed on,
+/// and the transfer is complete once both directions are. If either direction fails, or is
+/// [cancelled][DuplexTransfer::cancel], both sockets are shut down completely, so that the other
+/// direction stops too.
+/// # Example
+/// ```no_run
+/// use transfer_progress::DuplexTransfer;
+/// use std::net::{TcpListener, TcpStream};
+/// let listener = TcpListener::bind("127.0.0.1:8080")?;
+/// for client in listener.incoming() {
+///     let upstream = TcpStream::connect("example.com:80")?;
+///     let transfer = DuplexTransfer::new(client?, upstream)?;
+///     std::thread::spawn(move || {
+///         let _ = transfer.finish();
+///     });
+/// }
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct DuplexTransfer {
+    a_to_b: Arc<TransferState>,
+    b_to_a: Arc<TransferState>,
+    a_to_b_handle: thread::JoinHandle<Result<TcpStream, TransferError>>,
+    b_to_a_handle: thread::JoinHandle<Result<TcpStream, TransferError>>,
+}
+
+impl DuplexTransfer {
+    /// Creates and starts a new `DuplexTransfer` between `a` and `b`.
+    ///
+    /// Returns an error if either of the streams couldn't be
+    /// [cloned][TcpStream::try_clone] for use by both threads.
+    /// # Example
+    /// ```
+    /// use transfer_progress::DuplexTransfer;
+    /// use std::io::prelude::*;
+    /// use std::net::{Shutdown, TcpListener, TcpStream};
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let mut client = TcpStream::connect(listener.local_addr()?)?;
+    /// let (a, _) = listener.accept()?;
+    /// let mut server = TcpStream::connect(listener.local_addr()?)?;
+    /// let (b, _) = listener.accept()?;
+    /// let transfer = DuplexTransfer::new(a, b)?;
+    ///
+    /// client.write_all(b"ping")?;
+    /// client.shutdown(Shutdown::Write)?;
+    /// let mut request = Vec::new();
+    /// server.read_to_end(&mut request)?;
+    /// assert_eq!(request, b"ping");
+    /// server.write_all(b"pong!")?;
+    /// server.shutdown(Shutdown::Write)?;
+    /// let mut response = Vec::new();
+    /// client.read_to_end(&mut response)?;
+    /// assert_eq!(response, b"pong!");
+    ///
+    /// let (a_to_b, b_to_a) = (transfer.a_to_b(), transfer.b_to_a());
+    /// transfer.finish()?;
+    /// assert_eq!(a_to_b.transferred(), 4);
+    /// assert_eq!(b_to_a.transferred(), 5);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn new(a: TcpStream, b: TcpStream) -> io::Result<Self> {
+        let config = TransferConfig::default();
+        let a_writer = a.try_clone()?;
+        let b_writer = b.try_clone()?;
+        let sockets = Arc::new((a.try_clone()?, b.try_clone()?));
+        let a_to_b = Arc::new(TransferState::new(&config, None));
+        let b_to_a = Arc::new(TransferState::new(&config, None));
+        for state in [&a_to_b, &b_to_a] {
+            let sockets = Arc::clone(&sockets);
+            // Wake both directions up, which may be blocked reading or writing indefinitely
+            state.set_cancel_hook(move || {
+                let _ = sockets.0.shutdown(Shutdown::Both);
+                let _ = sockets.1.shutdown(Shutdown::Both);
+            });
+        }
+        let a_to_b_handle = spawn(
+            Arc::clone(&a_to_b),
+            Arc::clone(&b_to_a),
+            a,
+            b_writer,
+            config.buffer_size,
+        );
+        let b_to_a_handle = spawn(
+            Arc::clone(&b_to_a),
+            Arc::clone(&a_to_b),
+            b,
+            a_writer,
+            config.buffer_size,
+        );
+        Ok(Self {
+            a_to_b,
+            b_to_a,
+            a_to_b_handle,
+            b_to_a_handle,
+        })
+    }
+
+    /// Consumes the `DuplexTransfer`, blocking until both directions are complete.
+    ///
+    /// If both directions were successful, returns `Ok(a, b)`, otherwise returns the error from
+    /// the direction from `a` to `b` if it failed, or the one from `b` to `a` if not.
+    /// # Example
+    /// ```
+    /// # #[cfg(target_os = "linux")]
+    /// # fn main() -> std::io::Result<()> {
+    /// use transfer_progress::DuplexTransfer;
+    /// use std::io::{self, prelude::*};
+    /// use std::net::{TcpListener, TcpStream};
+    /// # use std::os::unix::io::AsRawFd;
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let mut client = TcpStream::connect(listener.local_addr()?)?;
+    /// let (a, _) = listener.accept()?;
+    /// // A server which never sends anything, or hangs up
+    /// let _server = TcpStream::connect(listener.local_addr()?)?;
+    /// let (b, _) = listener.accept()?;
+    /// let transfer = DuplexTransfer::new(a, b)?;
+    /// client.write_all(b"ping")?;
+    /// // Reset the client's connection, rather than closing it cleanly
+    /// # let linger = libc::linger { l_onoff: 1, l_linger: 0 };
+    /// # let len = std::mem::size_of_val(&linger) as libc::socklen_t;
+    /// # let linger = &linger as *const libc::linger as *const libc::c_void;
+    /// # unsafe {
+    /// #     libc::setsockopt(client.as_raw_fd(), libc::SOL_SOCKET, libc::SO_LINGER, linger, len)
+    /// # };
+    /// drop(client);
+    /// let err = transfer.finish().unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # fn main() {}
+    /// ```
+    pub fn finish(self) -> io::Result<(TcpStream, TcpStream)> {
+        let join = |handle: thread::JoinHandle<Result<TcpStream, TransferError>>| {
+            handle
+                .join()
+                .unwrap_or_else(|payload| Err(TransferError::from_panic(payload)))
+        };
+        let a = join(self.a_to_b_handle);
+        let b = join(self.b_to_a_handle);
+        Ok((a?, b?))
+    }
+
+    /// Asks both directions of the transfer to stop as soon as possible, shutting down both
+    /// sockets, after which [`finish`][Self::finish] returns [`TransferError::Cancelled`].
+    /// Cancelling either direction through its [`ProgressHandle`] does the same. If the transfer
+    /// is already complete, this does nothing.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{DuplexTransfer, TransferError};
+    /// use std::net::{TcpListener, TcpStream};
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// // A client and server which never send anything, or hang up
+    /// let _client = TcpStream::connect(listener.local_addr()?)?;
+    /// let (a, _) = listener.accept()?;
+    /// let _server = TcpStream::connect(listener.local_addr()?)?;
+    /// let (b, _) = listener.accept()?;
+    /// let transfer = DuplexTransfer::new(a, b)?;
+    /// transfer.cancel();
+    /// let error = transfer.finish().err().unwrap();
+    /// let error = error.into_inner().unwrap().downcast::<TransferError>().unwrap();
+    /// assert!(matches!(*error, TransferError::Cancelled));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn cancel(&self) {
+        self.a_to_b.request_cancel();
+        self.b_to_a.request_cancel();
+    }
+
+    /// Tests if both directions of the transfer are complete.
+    pub fn is_complete(&self) -> bool {
+        self.a_to_b.is_complete() && self.b_to_a.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far in both directions together.
+    pub fn transferred(&self) -> u64 {
+        self.a_to_b.transferred() + self.b_to_a.transferred()
+    }
+
+    /// Returns the elapsed time since the transfer started, until both directions were complete.
+    pub fn running_time(&self) -> Duration {
+        self.a_to_b.running_time().max(self.b_to_a.running_time())
+    }
+
+    /// Returns the combined average speed of both directions, in bytes per second.
+    pub fn speed(&self) -> u64 {
+        (self.a_to_b.speed() + self.b_to_a.speed()).round() as u64
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring the data copied from `a` to `b`.
+    pub fn a_to_b(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.a_to_b))
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring the data copied from `b` to `a`.
+    pub fn b_to_a(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.b_to_a))
+    }
+}
+
+/// Starts a thread copying everything from `reader` to `writer`, keeping `state` up to date, which
+/// returns `reader` once it's done. The copy stops if either `state` or `other`, the state of the
+/// opposite direction, is cancelled.
+fn spawn(
+    state: Arc<TransferState>,
+    other: Arc<TransferState>,
+    mut reader: TcpStream,
+    mut writer: TcpStream,
+    buffer_size: usize,
+) -> thread::JoinHandle<Result<TcpStream, TransferError>> {
+    thread::spawn(move || {
+        let _complete = CompleteOnPanic(&state);
+        let mut res = copy(&state, &mut reader, &mut writer, buffer_size);
+        // Cancelling shuts the sockets down, so however the copy ended, that's why
+        if state.cancel_requested.load(Ordering::Acquire)
+            || other.cancel_requested.load(Ordering::Acquire)
+        {
+            res = Err(TransferError::Cancelled);
+        }
+        match &res {
+            Err(TransferError::Cancelled) => {
+                state.cancelled.store(true, Ordering::Release);
+                state.failed.store(true, Ordering::Release);
+            }
+            Err(e) => {
+                // Wake the other direction up, which may be waiting for data that will never come
+                let _ = reader.shutdown(Shutdown::Both);
+                let _ = writer.shutdown(Shutdown::Both);
+                state.set_failed(e);
+            }
+            Ok(()) => {}
+        }
+        state.set_complete();
+        res.map(|_| reader)
+    })
+}
+
+/// Copies everything from `reader` to `writer`, keeping `state` up to date, then shuts down the
+/// write half of `writer` to pass on the end of the stream.
+fn copy(
+    state: &TransferState,
+    reader: &mut TcpStream,
+    writer: &mut TcpStream,
+    buffer_size: usize,
+) -> Result<(), TransferError> {
+    let mut buf = vec![0; buffer_size];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        state.add_read(read as u64);
+        writer.write_all(&buf[..read])?;
+        state.add_written(read as u64);
+        state.chunks.fetch_add(1, Ordering::Release);
+    }
+    match writer.shutdown(Shutdown::Write) {
+        // The other side has already gone away, so there's nobody to tell
+        Err(e) if e.kind() != io::ErrorKind::NotConnected => Err(e.into()),
+        _ => Ok(()),
+    }
+}
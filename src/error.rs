@@ -0,0 +1,135 @@
+use std::{any::Any, error::Error, fmt, io};
+
+/// An error which caused a transfer to fail.
+///
+/// Methods which return an [`io::Error`] instead convert this into one, with an
+/// [`ErrorKind`][io::ErrorKind] matching the variant, from which it can be recovered with
+/// [`io::Error::into_inner`] and [`downcast`][Box::downcast].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TransferError {
+    /// Reading from the reader or writing to the writer failed.
+    Io(io::Error),
+    /// The transfer was cancelled before it completed.
+    Cancelled,
+    /// The transfer didn't complete in time.
+    TimedOut,
+    /// The thread performing the transfer panicked. Contains the panic message, if it was a
+    /// string.
+    Panicked(Option<String>),
+    /// The transfer was configured incorrectly.
+    InvalidConfig(String),
+    /// The transferred data failed verification.
+    Verification(String),
+    /// The reader ran out of data after `transferred` bytes, before reaching the `expected` size
+    /// of the transfer.
+    ShortRead {
+        /// The size the transfer was expected to be.
+        expected: u64,
+        /// The number of bytes actually transferred.
+        transferred: u64,
+    },
+    /// The reader had more data after reaching the `expected` size of the transfer. The extra
+    /// data isn't transferred.
+    Overrun {
+        /// The size the transfer was expected to be.
+        expected: u64,
+    },
+}
+
+impl TransferError {
+    /// Creates a [`TransferError::Panicked`] from the payload of a panic.
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => Some(*message),
+            Err(payload) => payload.downcast_ref::<&str>().map(|&s| s.to_owned()),
+        };
+        Self::Panicked(message)
+    }
+
+    /// Returns a copy of the error. The copy of an I/O error has the same kind, message and OS
+    /// error code, but not the same inner error.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Self::Io(e) => Self::Io(match e.raw_os_error() {
+                Some(code) => io::Error::from_raw_os_error(code),
+                None => io::Error::new(e.kind(), e.to_string()),
+            }),
+            Self::Cancelled => Self::Cancelled,
+            Self::TimedOut => Self::TimedOut,
+            Self::Panicked(message) => Self::Panicked(message.clone()),
+            Self::InvalidConfig(message) => Self::InvalidConfig(message.clone()),
+            Self::Verification(message) => Self::Verification(message.clone()),
+            Self::ShortRead {
+                expected,
+                transferred,
+            } => Self::ShortRead {
+                expected: *expected,
+                transferred: *transferred,
+            },
+            Self::Overrun { expected } => Self::Overrun {
+                expected: *expected,
+            },
+        }
+    }
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error during transfer: {}", e),
+            Self::Cancelled => f.write_str("transfer was cancelled"),
+            Self::TimedOut => f.write_str("transfer timed out"),
+            Self::Panicked(Some(message)) => write!(f, "transfer thread panicked: {}", message),
+            Self::Panicked(None) => f.write_str("transfer thread panicked"),
+            Self::InvalidConfig(message) => {
+                write!(f, "invalid transfer configuration: {}", message)
+            }
+            Self::Verification(message) => write!(f, "transfer verification failed: {}", message),
+            Self::ShortRead {
+                expected,
+                transferred,
+            } => write!(
+                f,
+                "reader ended after {} bytes, but the transfer was expected to be {} bytes",
+                transferred, expected
+            ),
+            Self::Overrun { expected } => write!(
+                f,
+                "reader had more than the {} bytes the transfer was expected to be",
+                expected
+            ),
+        }
+    }
+}
+
+impl Error for TransferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TransferError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<TransferError> for io::Error {
+    fn from(e: TransferError) -> Self {
+        let kind = match e {
+            TransferError::Io(e) => return e,
+            TransferError::Cancelled => io::ErrorKind::Other,
+            TransferError::TimedOut => io::ErrorKind::TimedOut,
+            TransferError::Panicked(_) => io::ErrorKind::Other,
+            TransferError::InvalidConfig(_) => io::ErrorKind::InvalidInput,
+            TransferError::Verification(_) => io::ErrorKind::InvalidData,
+            TransferError::ShortRead { .. } => io::ErrorKind::UnexpectedEof,
+            TransferError::Overrun { .. } => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
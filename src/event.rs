@@ -0,0 +1,61 @@
+use crate::TransferId;
+
+/// An event in the life of a transfer, as delivered by
+/// [`Transfer::subscribe`][crate::Transfer::subscribe].
+///
+/// Each event carries the [ID][TransferId] of its transfer, so that events from several transfers
+/// merged into one stream can be told apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// More bytes have been transferred.
+    Progress {
+        /// The ID of the transfer.
+        id: TransferId,
+        /// The number of bytes transferred since the last event.
+        delta: u64,
+        /// The total number of bytes transferred so far.
+        transferred: u64,
+    },
+    /// The transfer completed successfully. This is always the last event.
+    Complete {
+        /// The ID of the transfer.
+        id: TransferId,
+        /// The total number of bytes transferred.
+        transferred: u64,
+    },
+    /// The transfer was [cancelled][crate::Transfer::cancel]. This is always the last event.
+    Cancelled {
+        /// The ID of the transfer.
+        id: TransferId,
+        /// The total number of bytes transferred before the transfer stopped.
+        transferred: u64,
+    },
+    /// The transfer failed. This is always the last event.
+    Failed {
+        /// The ID of the transfer.
+        id: TransferId,
+        /// The total number of bytes transferred before the transfer failed.
+        transferred: u64,
+        /// A description of the error. The error itself is returned by
+        /// [`Transfer::finish`][crate::Transfer::finish].
+        error: String,
+    },
+}
+
+impl ProgressEvent {
+    /// Tests if this is the last event of the transfer, after which no more are sent.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, Self::Progress { .. })
+    }
+
+    /// Returns the ID of the transfer this event is about.
+    pub fn id(&self) -> TransferId {
+        match self {
+            Self::Progress { id, .. }
+            | Self::Complete { id, .. }
+            | Self::Cancelled { id, .. }
+            | Self::Failed { id, .. } => *id,
+        }
+    }
+}
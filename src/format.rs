@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+#[cfg(all(feature = "bytesize", not(feature = "humansize")))]
+use bytesize::ByteSize;
+#[cfg(feature = "humansize")]
+use humansize::{FormatSizeOptions, BINARY, DECIMAL};
+
+/// Formats a number of bytes for humans, in SI units (multiples of 1000) if `si` is `true`, or
+/// IEC units (multiples of 1024) otherwise.
+///
+/// This uses the `humansize` feature if it's enabled, or else the `bytesize` feature. Without
+/// either, this falls back to the exact number of bytes.
+#[cfg(feature = "humansize")]
+pub(crate) fn bytes(bytes: u64, si: bool) -> String {
+    let options = FormatSizeOptions::from(if si { DECIMAL } else { BINARY })
+        // Match bytesize's output, like "2.0 MiB"
+        .decimal_places(1)
+        .decimal_zeroes(1);
+    humansize::format_size(bytes, options)
+}
+
+/// Formats a number of bytes for humans, in SI units (multiples of 1000) if `si` is `true`, or
+/// IEC units (multiples of 1024) otherwise.
+///
+/// This uses the `humansize` feature if it's enabled, or else the `bytesize` feature. Without
+/// either, this falls back to the exact number of bytes.
+#[cfg(all(feature = "bytesize", not(feature = "humansize")))]
+pub(crate) fn bytes(bytes: u64, si: bool) -> String {
+    // bytesize's flag selects IEC units when `true`, despite its name
+    ByteSize::b(bytes).to_string_as(!si)
+}
+
+/// Formats a number of bytes for humans, in SI units (multiples of 1000) if `si` is `true`, or
+/// IEC units (multiples of 1024) otherwise.
+///
+/// This uses the `humansize` feature if it's enabled, or else the `bytesize` feature. Without
+/// either, this falls back to the exact number of bytes.
+#[cfg(not(any(feature = "bytesize", feature = "humansize")))]
+pub(crate) fn bytes(bytes: u64, _si: bool) -> String {
+    format!("{} B", bytes)
+}
+
+/// Formats a speed of `bytes` per second for humans, without the "/s", as bytes like [`bytes`]
+/// does, or as bits, in SI units, like "12.3 Mbit", if `in_bits` is `true`.
+pub(crate) fn speed(bytes: u64, si: bool, in_bits: bool) -> String {
+    const UNITS: [&str; 6] = ["kbit", "Mbit", "Gbit", "Tbit", "Pbit", "Ebit"];
+    if !in_bits {
+        return self::bytes(bytes, si);
+    }
+    let bits = bytes.saturating_mul(8);
+    if bits < 1000 {
+        return format!("{} bit", bits);
+    }
+    let mut value = bits as f64 / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats `duration` like a clock, as `MM:SS`, or `H:MM:SS` if it's an hour or longer.
+pub(crate) fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// Formats `duration` for humans, to the nearest second below it, like `3m 41s`, leaving out any
+/// units which are zero, or as `0s` if it's shorter than a second.
+pub(crate) fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts = [
+        (secs / 86400, "d"),
+        (secs / 3600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+    ];
+    let formatted: Vec<String> = parts
+        .iter()
+        .filter(|&&(value, _)| value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted.join(" ")
+    }
+}
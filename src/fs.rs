@@ -0,0 +1,287 @@
+use std::{
+    fs::{self, File},
+    io::{self, prelude::*},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    cancel::CancelOnDrop,
+    check_cancelled, copy,
+    state::{CompleteOnPanic, TransferState},
+    ProgressHandle, TransferBuilder, TransferError,
+};
+
+/// Monitors the progress of copying a directory, and everything in it, to another directory.
+///
+/// The source directory is walked when the transfer is created, to find every file in it and their
+/// total size, then the files are copied one at a time, on a thread of their own. As well as the
+/// progress of the whole tree, the file being copied and its progress are available, through
+/// [`current_file`][DirTransfer::current_file] and
+/// [`current_file_progress`][DirTransfer::current_file_progress].
+///
+/// Only regular files and directories are copied: anything else, such as a symbolic link, is
+/// skipped. Each file is copied up to the size it had when the directory was walked. To configure
+/// the transfer, use [`TransferBuilder::build_dir`].
+/// # Example
+/// ```no_run
+/// use transfer_progress::DirTransfer;
+/// let transfer = DirTransfer::new("photos", "backup/photos")?;
+/// while !transfer.is_complete() {
+///     if let Some(file) = transfer.current_file() {
+///         println!(
+///             "{:.1}%: {}",
+///             transfer.fraction_transferred() * 100.0,
+///             file.display()
+///         );
+///     }
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// transfer.finish()?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct DirTransfer {
+    state: Arc<TransferState>,
+    /// The path and size of every file to copy, relative to the source directory, in the order
+    /// they're copied.
+    files: Arc<[(PathBuf, u64)]>,
+    handle: thread::JoinHandle<Result<(), TransferError>>,
+    /// The thread delivering updates to the [`on_update`][TransferBuilder::on_update] callback.
+    ticker: Option<thread::JoinHandle<()>>,
+    _cancel_on_drop: CancelOnDrop,
+}
+
+impl DirTransfer {
+    /// Creates and starts a new `DirTransfer`, copying the contents of the directory at `from`
+    /// into the directory at `to`, which is created if it doesn't exist.
+    ///
+    /// Files which already exist in `to` are replaced. Returns an error if `from` couldn't be
+    /// walked.
+    /// # Example
+    /// ```
+    /// use transfer_progress::DirTransfer;
+    /// use std::fs;
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("dir-transfer-from"), dir.join("dir-transfer-to"));
+    /// # let _ = fs::remove_dir_all(&from);
+    /// # let _ = fs::remove_dir_all(&to);
+    /// fs::create_dir_all(from.join("nested/empty"))?;
+    /// fs::write(from.join("a.txt"), "Hello")?;
+    /// fs::write(from.join("nested/b.txt"), "world!")?;
+    /// let transfer = DirTransfer::new(&from, &to)?;
+    /// assert_eq!(transfer.size(), 11);
+    /// assert_eq!(transfer.file_count(), 2);
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read_to_string(to.join("nested/b.txt"))?, "world!");
+    /// assert!(to.join("nested/empty").is_dir());
+    /// # fs::remove_dir_all(from)?;
+    /// # fs::remove_dir_all(to)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn new<P, Q>(from: P, to: Q) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        TransferBuilder::new().build_dir(from, to)
+    }
+
+    pub(crate) fn with_builder(
+        mut builder: TransferBuilder,
+        from: &Path,
+        to: &Path,
+    ) -> io::Result<Self> {
+        // There's no single stream of data to limit
+        if builder.config.limit.is_some() {
+            return Err(TransferError::InvalidConfig(
+                "directory transfers don't support limits".to_owned(),
+            )
+            .into());
+        }
+        let (from, to) = (from.to_owned(), to.to_owned());
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        walk(&from, Path::new(""), &mut dirs, &mut files)?;
+        let files: Arc<[(PathBuf, u64)]> = files.into();
+        builder.size = Some(files.iter().map(|&(_, size)| size).sum());
+        let parts = builder.into_parts();
+        let handle = {
+            let (state, files) = (Arc::clone(&parts.state), Arc::clone(&files));
+            let options = parts.options;
+            thread::spawn(move || {
+                let _complete = CompleteOnPanic(&state);
+                let res = copy::copy_each(&state, options, |index| {
+                    open(&state, &from, &to, &dirs, &files, index)
+                });
+                check_cancelled(&state, res)
+            })
+        };
+        Ok(Self {
+            _cancel_on_drop: CancelOnDrop::new(&parts.state, parts.config.cancel_on_drop),
+            state: parts.state,
+            files,
+            handle,
+            ticker: parts.ticker,
+        })
+    }
+
+    /// Consumes the `DirTransfer`, blocking until the transfer is complete.
+    ///
+    /// If a file couldn't be copied, returns the error, having left the files after it uncopied.
+    /// If the transfer was [cancelled][Self::cancel], returns [`TransferError::Cancelled`].
+    pub fn finish(self) -> io::Result<()> {
+        let res = self
+            .handle
+            .join()
+            .unwrap_or_else(|payload| Err(TransferError::from_panic(payload)));
+        if let Some(ticker) = self.ticker {
+            let _ = ticker.join();
+        }
+        res.map_err(io::Error::from)
+    }
+
+    /// Asks the transfer to stop as soon as possible, after which [`finish`][Self::finish]
+    /// returns [`TransferError::Cancelled`]. The file being copied is left incomplete.
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Pauses the transfer until it's [resumed][Self::resume], as
+    /// [`Transfer::pause`][crate::Transfer::pause] does.
+    pub fn pause(&self) {
+        self.state.pause();
+    }
+
+    /// Resumes the transfer after it has been [paused][Self::pause].
+    pub fn resume(&self) {
+        self.state.resume();
+    }
+
+    /// Tests if the transfer is [paused][Self::pause].
+    pub fn is_paused(&self) -> bool {
+        self.state.is_paused()
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far, from all the files together.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the total size of all the files being copied.
+    pub fn size(&self) -> u64 {
+        self.state.size().unwrap_or_default()
+    }
+
+    /// Returns a fraction between 0.0 and 1.0 representing the state of the whole transfer.
+    pub fn fraction_transferred(&self) -> f64 {
+        if self.size() == 0 {
+            return 1.0;
+        }
+        self.transferred().min(self.size()) as f64 / self.size() as f64
+    }
+
+    /// Returns the elapsed time since the transfer started.
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer.
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns the number of files being copied.
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns the number of files which have been copied in full.
+    pub fn files_completed(&self) -> usize {
+        let (index, _) = self.state.segment();
+        index
+    }
+
+    /// Returns the path of the file being copied, relative to the source directory, or `None` if
+    /// the transfer is complete.
+    pub fn current_file(&self) -> Option<&Path> {
+        if self.is_complete() {
+            return None;
+        }
+        let (index, _) = self.state.segment();
+        self.files.get(index).map(|(path, _)| path.as_path())
+    }
+
+    /// Returns the number of bytes transferred from the file being copied, and its size, or `None`
+    /// if the transfer is complete.
+    pub fn current_file_progress(&self) -> Option<(u64, u64)> {
+        if self.is_complete() {
+            return None;
+        }
+        let (index, transferred) = self.state.segment();
+        self.files
+            .get(index)
+            .map(|&(_, size)| (transferred.min(size), size))
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring the whole transfer.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+}
+
+/// Adds every directory and regular file under `dir`, which is `relative` to the root of the
+/// walk, to `dirs` and `files`, sorted by name, with directories before their contents.
+fn walk(
+    dir: &Path,
+    relative: &Path,
+    dirs: &mut Vec<PathBuf>,
+    files: &mut Vec<(PathBuf, u64)>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        let path = relative.join(entry.file_name());
+        if file_type.is_dir() {
+            dirs.push(path.clone());
+            walk(&entry.path(), &path, dirs, files)?;
+        } else if file_type.is_file() {
+            files.push((path, entry.metadata()?.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Opens the file at `index` in `files` under `from`, and creates it under `to`, recording that
+/// it's the file being copied in `state`, or returns `None` once every file has been copied. The
+/// first call creates `dirs` under `to` first.
+fn open(
+    state: &TransferState,
+    from: &Path,
+    to: &Path,
+    dirs: &[PathBuf],
+    files: &[(PathBuf, u64)],
+    index: usize,
+) -> Result<Option<(io::Take<File>, File)>, TransferError> {
+    if index == 0 {
+        fs::create_dir_all(to)?;
+        for dir in dirs {
+            fs::create_dir_all(to.join(dir))?;
+        }
+    }
+    state.start_segment(index);
+    let (path, size) = match files.get(index) {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let reader = File::open(from.join(path))?.take(*size);
+    let writer = File::create(to.join(path))?;
+    Ok(Some((reader, writer)))
+}
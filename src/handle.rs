@@ -0,0 +1,160 @@
+use std::{
+    fmt,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use crate::{state::TransferState, TransferId};
+
+/// A cheap, cloneable handle for monitoring a transfer, independent of the types of its reader and
+/// writer, as returned by [`Transfer::progress_handle`][crate::Transfer::progress_handle].
+///
+/// This makes it possible to keep track of many different kinds of transfer together, for
+/// instance in a [`Vec`], and to monitor a transfer from several places at once. A handle stays
+/// valid after the transfer is finished, reporting its final progress.
+/// # Example
+/// ```
+/// use transfer_progress::{ProgressHandle, Transfer};
+/// use std::io;
+/// let transfer1 = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+/// let transfer2 = Transfer::new(io::empty(), Vec::new());
+/// let handles: Vec<ProgressHandle> =
+///     vec![transfer1.progress_handle(), transfer2.progress_handle()];
+/// transfer1.finish()?;
+/// transfer2.finish()?;
+/// assert!(handles.iter().all(|handle| handle.is_complete()));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct ProgressHandle {
+    state: Arc<TransferState>,
+}
+
+impl ProgressHandle {
+    pub(crate) fn new(state: Arc<TransferState>) -> Self {
+        Self { state }
+    }
+
+    /// Returns the transfer's unique ID.
+    pub fn id(&self) -> TransferId {
+        self.state.id
+    }
+
+    /// Returns the transfer's [name][crate::TransferBuilder::name], if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.state.name.as_deref()
+    }
+
+    /// Asks the transfer to stop as soon as possible, as for
+    /// [`Transfer::cancel`][crate::Transfer::cancel]. If the transfer is already complete, this
+    /// does nothing.
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Tests if the transfer ended with an error.
+    pub fn is_failed(&self) -> bool {
+        self.state.failed.load(Ordering::Acquire) && !self.is_cancelled()
+    }
+
+    /// Tests if the transfer was [cancelled][crate::Transfer::cancel].
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the size of the transfer, if it's known.
+    pub fn size(&self) -> Option<u64> {
+        self.state.size()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns the elapsed time since the transfer started, or zero if timing isn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the time between the transfer being created and the first bytes being read from
+    /// the reader, or `None` if nothing has been read yet, or timing isn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::empty(), io::sink());
+    /// let handle = transfer.progress_handle();
+    /// transfer.finish()?;
+    /// assert_eq!(handle.time_to_first_byte(), None);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn time_to_first_byte(&self) -> Option<Duration> {
+        self.state.time_to_first_byte()
+    }
+
+    /// Returns the number of chunks transferred thus far, as returned by
+    /// [`Transfer::chunks`][crate::Transfer::chunks].
+    pub fn chunks(&self) -> u64 {
+        self.state.chunks.load(Ordering::Acquire)
+    }
+
+    /// Returns the average number of bytes transferred per [chunk][Self::chunks], or `None` if no
+    /// chunks have been transferred yet.
+    pub fn average_chunk_size(&self) -> Option<u64> {
+        self.state.average_chunk_size()
+    }
+
+    /// Returns the length of the largest chunk read from the reader, or `None` if no chunks have
+    /// been read yet.
+    pub fn max_chunk_size(&self) -> Option<u64> {
+        self.state.max_chunk_size()
+    }
+
+    /// Returns the fraction of the transfer that is complete, between 0.0 and 1.0, or `None` if
+    /// the size of the transfer isn't known.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{SizedTransfer, Transfer};
+    /// use std::io;
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+    /// let handle = transfer.progress_handle();
+    /// transfer.finish()?;
+    /// assert_eq!(handle.fraction_transferred(), Some(1.0));
+    /// let transfer = Transfer::new(io::empty(), io::sink());
+    /// assert_eq!(transfer.progress_handle().fraction_transferred(), None);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn fraction_transferred(&self) -> Option<f64> {
+        let size = self.size()?;
+        if size == 0 {
+            return Some(1.0);
+        }
+        Some(self.transferred().min(size) as f64 / size as f64)
+    }
+}
+
+impl fmt::Debug for ProgressHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressHandle")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("transferred", &self.transferred())
+            .field("size", &self.size())
+            .field("complete", &self.is_complete())
+            .finish()
+    }
+}
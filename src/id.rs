@@ -0,0 +1,42 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A number identifying a transfer, unique among all the transfers created by a process.
+///
+/// Every transfer is assigned one when it's created, in increasing order. It's displayed like
+/// `#3`, and can be used to tell transfers apart, for instance in a
+/// [`TransferManager`][crate::TransferManager], even if they don't have
+/// [names][crate::TransferBuilder::name].
+/// # Example
+/// ```
+/// use transfer_progress::Transfer;
+/// use std::io;
+/// let transfer1 = Transfer::new(io::empty(), io::sink());
+/// let transfer2 = Transfer::new(io::empty(), io::sink());
+/// assert!(transfer1.id() < transfer2.id());
+/// assert_eq!(transfer1.id().to_string(), format!("#{}", transfer1.id().get()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransferId(u64);
+
+impl TransferId {
+    /// Returns a new ID, which hasn't been returned before.
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the ID as a number.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for TransferId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
@@ -1,34 +1,112 @@
-#[cfg(feature = "bytesize")]
-use std::fmt;
 use std::{
+    fmt,
+    fs::File,
     io::{self, prelude::*},
-    sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering},
-        Arc,
-    },
+    path::Path,
+    sync::{atomic::Ordering, mpsc, Arc, Mutex, Weak},
     thread,
     time::{Duration, Instant},
 };
 
-#[cfg(feature = "bytesize")]
-use bytesize::ByteSize;
+#[cfg(feature = "tokio")]
+mod async_transfer;
+#[cfg(feature = "tokio")]
+pub use async_transfer::AsyncTransfer;
+#[cfg(feature = "tokio")]
+mod bridge;
+#[cfg(feature = "tokio")]
+pub use bridge::{bridge_transfer, BridgeTransfer};
+mod builder;
+mod cancel;
+pub use builder::{TransferBuilder, TransferConfig, ZeroReadPolicy};
+use cancel::CancelOnDrop;
+pub use cancel::CancelToken;
+mod chunks;
+pub use chunks::Chunks;
 use progress_streams::ProgressReader;
-
-#[derive(Default)]
-struct TransferState {
-    transferred: AtomicU64,
-    complete: AtomicBool,
-}
+mod copy;
+use copy::CopyOptions;
+mod digest;
+#[cfg(feature = "digest")]
+pub use digest::DigestKind;
+mod duplex;
+pub use duplex::DuplexTransfer;
+mod error;
+pub use error::TransferError;
+mod event;
+pub use event::ProgressEvent;
+mod format;
+mod fs;
+pub use fs::DirTransfer;
+mod handle;
+pub use handle::ProgressHandle;
+mod id;
+pub use id::TransferId;
+mod limiter;
+pub use limiter::BandwidthLimiter;
+mod manager;
+pub use manager::TransferManager;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod outcome;
+pub use outcome::Outcome;
+mod parallel;
+pub use parallel::ParallelTransfer;
+mod progress;
+pub use progress::Progress;
+#[cfg(feature = "indicatif")]
+mod progress_bar;
+mod queue;
+pub use queue::{QueueResult, TransferQueue};
+mod readahead;
+mod render;
+use readahead::Readahead;
+mod retry;
+pub use retry::RetryPolicy;
+mod schedule;
+mod scoped;
+pub use schedule::TimeWindow;
+pub use scoped::ScopedTransfer;
+mod sequence;
+pub use sequence::Sequence;
+mod snapshot;
+pub use snapshot::ProgressSnapshot;
+mod state;
+use state::{CompleteOnPanic, TransferState};
+mod tee;
+mod template;
+pub use tee::Tee;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use uring::{UringDriver, UringTransfer};
+mod zero_copy;
+use zero_copy::ZeroCopy;
 
 /// Monitors the progress of a transfer from a [reader][Read] to a [writer][Write].
+///
+/// Dropping a `Transfer` without [finishing][Transfer::finish] it lets the transfer carry on in
+/// the background, as if it had been [detached][Transfer::detach], unless
+/// [`cancel_on_drop`][TransferBuilder::cancel_on_drop] is enabled.
+///
+/// With the `tracing` feature enabled, each transfer runs inside a `transfer` span, recording its
+/// [ID][TransferId], and its name and size if known. A `trace` event with the bytes transferred
+/// and the speed is emitted about once a second while data is moving, and an `info` event with the
+/// final statistics once the transfer completes, or a `warn` event if it fails.
 pub struct Transfer<R, W>
 where
     R: Read + Send + 'static,
     W: Write + Send + 'static,
 {
-    start_time: Instant,
     state: Arc<TransferState>,
-    handle: thread::JoinHandle<io::Result<(R, W)>>,
+    handle: thread::JoinHandle<Result<(R, W), TransferError>>,
+    /// The thread delivering updates to the [`on_update`][TransferBuilder::on_update] callback.
+    ticker: Option<thread::JoinHandle<()>>,
+    /// The threads started by [`print_every`][Transfer::print_every]. They stop early once they
+    /// can no longer upgrade a weak reference to this, because the `Transfer` has been dropped.
+    printers: Arc<Mutex<Vec<thread::JoinHandle<()>>>>,
+    config: TransferConfig,
+    cancel_on_drop: CancelOnDrop,
 }
 
 impl<R, W> Transfer<R, W>
@@ -46,33 +124,157 @@ where
     /// let transfer = Transfer::new(reader, writer);
     /// # Ok::<_, std::io::Error>(())
     /// ```
-    pub fn new(reader: R, mut writer: W) -> Self {
-        let state = Arc::new(TransferState::default());
-        let state_clone = Arc::clone(&state);
-        let handle = thread::spawn(move || -> io::Result<(R, W)> {
-            let mut reader = ProgressReader::new(reader, |bytes| {
-                // If someone would like to confirm the correctness of the ordering guarantees, that would
-                // be much appreciated.
-                state_clone
-                    .transferred
-                    .fetch_add(bytes as u64, Ordering::Release);
-            });
-            // We need to store the result and bubble it later so we can set the complete flag.
-            let res = io::copy(&mut reader, &mut writer);
-            state_clone.complete.store(true, Ordering::Release);
-            res.map(|_| (reader.into_inner(), writer))
+    pub fn new(reader: R, writer: W) -> Self {
+        TransferBuilder::new().build(reader, writer)
+    }
+
+    /// Creates and starts a new `Transfer` which stops after reading `delimiter`, leaving the
+    /// reader positioned just after it. The delimiter is only written to the writer if `inclusive`
+    /// is `true`, but is always read from the reader. If the delimiter is never read, the whole
+    /// reader is transferred, just like [`new`][Transfer::new].
+    ///
+    /// Only the bytes written are counted as transferred.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io::{self, Read};
+    /// let reader = io::Cursor::new(b"first record\nsecond record\n".to_vec());
+    /// let transfer = Transfer::until_delimiter(reader, Vec::new(), b'\n', false);
+    /// let (mut reader, writer) = transfer.finish()?;
+    /// assert_eq!(writer, b"first record");
+    /// let mut rest = String::new();
+    /// reader.read_to_string(&mut rest)?;
+    /// assert_eq!(rest, "second record\n");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn until_delimiter(reader: R, writer: W, delimiter: u8, inclusive: bool) -> Self
+    where
+        R: BufRead,
+    {
+        TransferBuilder::new().build_until_delimiter(reader, writer, delimiter, inclusive)
+    }
+
+    /// Creates and starts a new `Transfer` which reconnects to its source if reading from it
+    /// fails, carrying on from where it stopped.
+    ///
+    /// `connect` is called on the transfer's thread with the number of bytes read so far, to make
+    /// the first reader, and again to make a new one after reading fails, or `connect` itself
+    /// does, as allowed by `policy`. The new reader must start from that offset in the source, for
+    /// instance by reopening a file and seeking, or making an HTTP range request. Failures of the
+    /// writer aren't retried.
+    ///
+    /// If the transfer is [cancelled][Self::cancel] while it's waiting to retry, there's no reader
+    /// to hand back, so [`finish_or_cancelled`][Self::finish_or_cancelled] returns
+    /// [`TransferError::Cancelled`] as an error.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{RetryPolicy, Transfer};
+    /// use std::io::{self, Read};
+    /// use std::time::Duration;
+    /// /// A reader whose connection drops after `fail_after` bytes.
+    /// struct Flaky {
+    ///     inner: io::Cursor<Vec<u8>>,
+    ///     fail_after: u64,
+    /// }
+    /// impl Read for Flaky {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         if self.inner.position() >= self.fail_after {
+    ///             return Err(io::ErrorKind::ConnectionReset.into());
+    ///         }
+    ///         self.inner.read(buf)
+    ///     }
+    /// }
+    /// let data: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    /// let source = data.clone();
+    /// let transfer = Transfer::with_retries(
+    ///     move |offset| {
+    ///         Ok(Flaky {
+    ///             inner: io::Cursor::new(source[offset as usize..].to_vec()),
+    ///             fail_after: 1024,
+    ///         })
+    ///     },
+    ///     Vec::new(),
+    ///     RetryPolicy::new(3).initial_backoff(Duration::from_millis(1)),
+    /// );
+    /// let (_, writer) = transfer.finish()?;
+    /// assert_eq!(writer, data);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn with_retries<F>(connect: F, writer: W, policy: RetryPolicy) -> Self
+    where
+        F: FnMut(u64) -> io::Result<R> + Send + 'static,
+    {
+        TransferBuilder::new().build_with_retries(connect, writer, policy)
+    }
+
+    pub(crate) fn with_builder(builder: TransferBuilder, reader: R, writer: W) -> Self {
+        Self::with_builder_and(builder, reader, writer, |_, _| {})
+    }
+
+    /// Like [`with_builder`][Self::with_builder], but calls `prepare` with the transfer's state and
+    /// the reader on the transfer's thread, before the transfer starts.
+    pub(crate) fn with_builder_and<F>(
+        builder: TransferBuilder,
+        mut reader: R,
+        writer: W,
+        prepare: F,
+    ) -> Self
+    where
+        F: FnOnce(&Arc<TransferState>, &mut R) + Send + 'static,
+    {
+        let readahead = if builder.config.readahead {
+            Readahead::for_reader(&reader)
+        } else {
+            None
+        };
+        let limit = builder.copy_limit();
+        let zero_copy = if builder.wants_zero_copy() && limit.is_none() {
+            ZeroCopy::for_pair(&reader, &writer)
+        } else {
+            None
+        };
+        Self::spawn(builder, move |state, mut options| {
+            options.readahead = readahead;
+            options.zero_copy = zero_copy;
+            prepare(state, &mut reader);
+            match limit {
+                Some((limit, exact)) => {
+                    copy::copy_limited(state, options, reader, writer, limit, exact)
+                }
+                None => copy::copy(state, options, reader, writer),
+            }
+        })
+    }
+
+    /// Spawns a thread to perform the transfer with `copy_fn`.
+    pub(crate) fn spawn<F>(builder: TransferBuilder, copy_fn: F) -> Self
+    where
+        F: FnOnce(&Arc<TransferState>, CopyOptions) -> Result<(R, W), TransferError>
+            + Send
+            + 'static,
+    {
+        let parts = builder.into_parts();
+        let state = Arc::clone(&parts.state);
+        let options = parts.options;
+        let handle = thread::spawn(move || {
+            let _complete = CompleteOnPanic(&state);
+            copy_fn(&state, options)
         });
         Self {
-            start_time: Instant::now(),
-            state,
+            cancel_on_drop: CancelOnDrop::new(&parts.state, parts.config.cancel_on_drop),
+            state: parts.state,
             handle,
+            ticker: parts.ticker,
+            printers: Arc::default(),
+            config: parts.config,
         }
     }
 
     /// Consumes the `Transfer`, blocking until the transfer is complete.
     ///
     /// If the transfer was successful, returns `Ok(reader, writer)`, otherwise returns
-    /// the error.
+    /// the error. If the thread performing the transfer panicked, for instance because the reader
+    /// did, returns [`TransferError::Panicked`] rather than panicking.
     ///
     /// If the transfer is already complete, returns immediately.
     /// # Example
@@ -85,8 +287,344 @@ where
     /// let (reader, writer) = transfer.finish()?;
     /// # Ok::<_, std::io::Error>(())
     /// ```
+    /// A panicking reader:
+    /// ```
+    /// use transfer_progress::{Transfer, TransferError};
+    /// use std::io::{self, Read};
+    /// let transfer = Transfer::new(PanickingReader, io::sink());
+    /// let error = transfer.finish().err().unwrap();
+    /// let error = error.into_inner().unwrap().downcast::<TransferError>().unwrap();
+    /// assert!(matches!(*error, TransferError::Panicked(_)));
+    /// # struct PanickingReader;
+    /// # impl Read for PanickingReader {
+    /// #     fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+    /// #         panic!("reader panicked")
+    /// #     }
+    /// # }
+    /// ```
     pub fn finish(self) -> io::Result<(R, W)> {
-        self.handle.join().unwrap()
+        let state = Arc::clone(&self.state);
+        check_cancelled(&state, self.join()).map_err(io::Error::from)
+    }
+
+    /// Like [`finish`][Self::finish], but also returns the digest of the data transferred, if one
+    /// was [requested][TransferBuilder::digest].
+    /// # Example
+    /// ```
+    /// use transfer_progress::{DigestKind, TransferBuilder};
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .digest(DigestKind::Sha256)
+    ///     .build(io::empty(), io::sink());
+    /// let (_, _, digest) = transfer.finish_with_digest()?;
+    /// assert_eq!(digest.map(|digest| digest.len()), Some(32));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    #[cfg(feature = "digest")]
+    pub fn finish_with_digest(self) -> io::Result<(R, W, Option<Vec<u8>>)> {
+        let state = Arc::clone(&self.state);
+        let (reader, writer) = self.finish()?;
+        Ok((reader, writer, state.digest()))
+    }
+
+    /// Returns the result of the transfer, like [`finish`][Self::finish], if it's complete, or
+    /// gives back the `Transfer` if it's still running, without blocking.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let mut transfer = Transfer::new(io::Cursor::new(vec![0; 4096]), io::sink());
+    /// let (reader, writer) = loop {
+    ///     match transfer.try_finish() {
+    ///         Ok(result) => break result?,
+    ///         Err(running) => transfer = running,
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_millis(1));
+    /// };
+    /// assert_eq!(reader.position(), 4096);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    // The signature mirrors `finish`, handing back the transfer itself if it isn't complete
+    #[allow(clippy::result_large_err)]
+    pub fn try_finish(self) -> Result<io::Result<(R, W)>, Self> {
+        if self.handle.is_finished() {
+            Ok(self.finish())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Consumes the `Transfer`, blocking until the transfer is complete, and returns the reader and
+    /// writer whether it completed or was [cancelled][Transfer::cancel].
+    ///
+    /// Unlike [`finish`][Self::finish], which returns [`TransferError::Cancelled`] if the transfer
+    /// was cancelled, this returns [`Outcome::Cancelled`], along with the reader and writer, so
+    /// that they can be cleaned up. Any other error is returned as usual.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{Outcome, Transfer};
+    /// use std::fs::{self, File};
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// transfer.cancel();
+    /// if let Outcome::Cancelled(_, writer) = transfer.finish_or_cancelled()? {
+    ///     // Remove the partially written file
+    ///     drop(writer);
+    ///     fs::remove_file("file2.txt")?;
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn finish_or_cancelled(self) -> io::Result<Outcome<R, W>> {
+        let state = Arc::clone(&self.state);
+        let (reader, writer) = self.join()?;
+        if state.cancelled.load(Ordering::Acquire) {
+            Ok(Outcome::Cancelled(reader, writer))
+        } else {
+            Ok(Outcome::Complete(reader, writer))
+        }
+    }
+
+    /// Asks the transfer to stop as soon as possible.
+    ///
+    /// The transfer stops before copying its next chunk, after which [`finish`][Self::finish]
+    /// returns [`TransferError::Cancelled`], or [`finish_or_cancelled`][Self::finish_or_cancelled]
+    /// returns the reader and writer. If the transfer is already complete, this does nothing.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// // A reader which never ends
+    /// let transfer = Transfer::new(io::repeat(0), io::sink());
+    /// transfer.wait_for_transferred(1024);
+    /// transfer.cancel();
+    /// let outcome = transfer.finish_or_cancelled()?;
+    /// assert!(outcome.is_cancelled());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    /// Recovering the cancellation from the error returned by [`finish`][Self::finish]:
+    /// ```
+    /// use transfer_progress::{Transfer, TransferError};
+    /// use std::io;
+    /// let transfer = Transfer::new(io::repeat(0), io::sink());
+    /// transfer.cancel();
+    /// let error = transfer.finish().err().unwrap();
+    /// assert_eq!(error.kind(), io::ErrorKind::Other);
+    /// let error = error.into_inner().unwrap().downcast::<TransferError>().unwrap();
+    /// assert!(matches!(*error, TransferError::Cancelled));
+    /// ```
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Pauses the transfer until it's [resumed][Transfer::resume].
+    ///
+    /// The transfer pauses before copying its next chunk. Time spent paused isn't counted by
+    /// [`running_time`][Transfer::running_time], so it doesn't drag down
+    /// [`speed`][Transfer::speed]. A paused transfer can still be [cancelled][Transfer::cancel].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// use std::time::Duration;
+    /// let transfer = Transfer::new(io::repeat(0), io::sink());
+    /// transfer.pause();
+    /// assert!(transfer.is_paused());
+    /// let transferred = transfer.transferred();
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// // At most one more chunk was copied before pausing
+    /// assert!(transfer.transferred() - transferred <= 8192);
+    /// transfer.resume();
+    /// transfer.cancel();
+    /// # transfer.finish_or_cancelled()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn pause(&self) {
+        self.state.pause();
+    }
+
+    /// Resumes the transfer after it has been [paused][Transfer::pause].
+    pub fn resume(&self) {
+        self.state.resume();
+    }
+
+    /// Tests if the transfer is [paused][Transfer::pause].
+    pub fn is_paused(&self) -> bool {
+        self.state.is_paused()
+    }
+
+    /// Consumes the `Transfer`, blocking until the transfer is complete, and returns its result
+    /// along with a snapshot of its final statistics.
+    ///
+    /// The snapshot is returned whether or not the transfer was successful, so, for instance, the
+    /// number of bytes transferred before an error can be logged. Unlike
+    /// [`finish`][Self::finish], if the transfer's thread panicked, this returns
+    /// [`TransferError::Panicked`] rather than panicking.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io::{self, Read};
+    /// // Fails after reading 1 KiB
+    /// let reader = io::Cursor::new(vec![0; 1024]).chain(FailingReader);
+    /// let transfer = Transfer::new(reader, io::sink());
+    /// let (result, stats) = transfer.finish_with_stats();
+    /// assert!(result.is_err());
+    /// assert_eq!(stats.transferred, 1024);
+    /// assert!(stats.complete);
+    /// # struct FailingReader;
+    /// # impl Read for FailingReader {
+    /// #     fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+    /// #         Err(io::Error::new(io::ErrorKind::Other, "connection lost"))
+    /// #     }
+    /// # }
+    /// ```
+    pub fn finish_with_stats(self) -> (Result<(R, W), TransferError>, ProgressSnapshot) {
+        let state = Arc::clone(&self.state);
+        let result = check_cancelled(&state, self.join());
+        (result, state.snapshot())
+    }
+
+    /// Waits for the transfer's thread, and then the thread delivering updates, if any, to exit,
+    /// turning a panic in the former into a [`TransferError::Panicked`].
+    fn join(self) -> Result<(R, W), TransferError> {
+        let state = &self.state;
+        let result = self.handle.join().unwrap_or_else(|payload| {
+            let e = TransferError::from_panic(payload);
+            // Now we have the panic message, record it in place of the generic error
+            state.set_failed(&e);
+            Err(e)
+        });
+        if let Some(ticker) = self.ticker {
+            let _ = ticker.join();
+        }
+        for printer in self.printers.lock().unwrap().drain(..) {
+            let _ = printer.join();
+        }
+        result
+    }
+
+    /// Returns a channel which receives a [`ProgressEvent`] every `interval` in which bytes were
+    /// transferred, followed by one saying how the transfer ended.
+    ///
+    /// The events are sent by a separate thread, which exits once the transfer ends or the
+    /// receiver is dropped. Each call returns a new, independent channel.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{ProgressEvent, Transfer};
+    /// use std::io;
+    /// use std::time::Duration;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024 * 1024]), io::sink());
+    /// let mut total = 0;
+    /// for event in transfer.subscribe(Duration::from_millis(10)) {
+    ///     match event {
+    ///         ProgressEvent::Progress { delta, .. } => total += delta,
+    ///         ProgressEvent::Complete { id, transferred } => {
+    ///             assert_eq!(id, transfer.id());
+    ///             assert_eq!(transferred, 1024 * 1024);
+    ///         }
+    ///         event => panic!("transfer ended unexpectedly: {:?}", event),
+    ///     }
+    /// }
+    /// assert_eq!(total, 1024 * 1024);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn subscribe(&self, interval: Duration) -> mpsc::Receiver<ProgressEvent> {
+        let (sender, receiver) = mpsc::channel();
+        let state = Arc::clone(&self.state);
+        thread::spawn(move || {
+            let mut last = 0;
+            loop {
+                // Check completion first, so we don't miss bytes transferred just before completing
+                let complete = state.wait_complete_timeout(interval);
+                let transferred = state.transferred();
+                if transferred > last {
+                    let event = ProgressEvent::Progress {
+                        id: state.id,
+                        delta: transferred - last,
+                        transferred,
+                    };
+                    if sender.send(event).is_err() {
+                        // Nobody's listening any more
+                        return;
+                    }
+                    last = transferred;
+                }
+                if complete {
+                    let id = state.id;
+                    let event = if state.cancelled.load(Ordering::Acquire) {
+                        ProgressEvent::Cancelled { id, transferred }
+                    } else if let Some(error) = state.error() {
+                        ProgressEvent::Failed {
+                            id,
+                            transferred,
+                            error,
+                        }
+                    } else {
+                        ProgressEvent::Complete { id, transferred }
+                    };
+                    let _ = sender.send(event);
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Returns the transfer's unique [ID][TransferId].
+    pub fn id(&self) -> TransferId {
+        self.state.id
+    }
+
+    /// Returns the transfer's [name][TransferBuilder::name], if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.state.name.as_deref()
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring this transfer, which doesn't depend on the
+    /// types of its reader and writer.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+
+    /// Consumes the `Transfer`, letting it carry on in the background, and returns a
+    /// [`ProgressHandle`] for monitoring it.
+    ///
+    /// The reader and writer are dropped by the transfer's thread once it ends, and any error is
+    /// only reported through the handle, by [`is_failed`][ProgressHandle::is_failed].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// let handle = transfer.detach();
+    /// while !handle.is_complete() {
+    ///     std::thread::yield_now();
+    /// }
+    /// assert_eq!(handle.transferred(), 1024);
+    /// assert!(!handle.is_failed());
+    /// ```
+    pub fn detach(mut self) -> ProgressHandle {
+        self.cancel_on_drop.disarm();
+        // Dropping the join handles detaches the threads
+        ProgressHandle::new(self.state)
+    }
+
+    /// Returns the configuration this transfer was created with.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .readahead(true)
+    ///     .build(reader, writer);
+    /// assert!(transfer.config().readahead);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn config(&self) -> &TransferConfig {
+        &self.config
     }
 
     /// Tests if the transfer is complete
@@ -105,9 +643,7 @@ where
     /// # Ok::<_, std::io::Error>(())
     /// ```
     pub fn is_complete(&self) -> bool {
-        // If someone would like to confirm the correctness of the ordering guarantees, that would
-        // be much appreciated.
-        self.state.complete.load(Ordering::Acquire)
+        self.state.is_complete()
     }
 
     /// Returns the number of bytes transferred thus far between the reader and the writer.
@@ -125,81 +661,1095 @@ where
     /// println!("Complete!");
     /// # Ok::<_, std::io::Error>(())
     /// ```
-    pub fn transferred(&self) -> u64 {
-        // If someone would like to confirm the correctness of the ordering guarantees, that would
-        // be much appreciated.
-        self.state.transferred.load(Ordering::Acquire)
+    ///
+    /// The count only ever goes up, even with a reader which misbehaves by returning tiny reads and
+    /// spurious errors. In debug builds, the transfer panics if it would go down:
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io::{self, Read};
+    /// // Reads a few bytes at a time, and is interrupted every other time
+    /// struct Flaky {
+    ///     data: io::Cursor<Vec<u8>>,
+    ///     reads: usize,
+    /// }
+    /// impl Read for Flaky {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         self.reads += 1;
+    ///         if self.reads % 2 == 0 {
+    ///             return Err(io::ErrorKind::Interrupted.into());
+    ///         }
+    ///         let len = buf.len().min(self.reads % 7 + 1);
+    ///         self.data.read(&mut buf[..len])
+    ///     }
+    /// }
+    /// let reader = Flaky {
+    ///     data: io::Cursor::new(vec![0; 64 * 1024]),
+    ///     reads: 0,
+    /// };
+    /// let transfer = Transfer::new(reader, io::sink());
+    /// let mut last = 0;
+    /// while !transfer.is_complete() {
+    ///     let transferred = transfer.transferred();
+    ///     assert!(transferred >= last);
+    ///     last = transferred;
+    /// }
+    /// assert_eq!(transfer.transferred(), 64 * 1024);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the number of bytes read from the reader thus far.
+    ///
+    /// Along with [`written_bytes`][Self::written_bytes], this shows how much data is in flight
+    /// between the reader and the writer. Unlike [`transferred`][Self::transferred], this
+    /// doesn't include any [offset][TransferBuilder::offset].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 4096]), io::sink());
+    /// transfer.wait();
+    /// assert_eq!(transfer.read_bytes(), 4096);
+    /// assert_eq!(transfer.written_bytes(), 4096);
+    /// ```
+    pub fn read_bytes(&self) -> u64 {
+        self.state.read()
+    }
+
+    /// Returns the number of bytes successfully written to the writer thus far.
+    ///
+    /// Unlike [`transferred`][Self::transferred], this doesn't include any
+    /// [offset][TransferBuilder::offset].
+    pub fn written_bytes(&self) -> u64 {
+        self.state.written()
+    }
+
+    /// Tests if the transfer has stalled, going without transferring any bytes for the
+    /// [stall threshold][TransferBuilder::stall_threshold].
+    ///
+    /// This tells a slow transfer, which is still making progress, from one which has hung. A
+    /// transfer isn't considered stalled while it's [paused][Transfer::pause], or once it's
+    /// complete.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::{io, sync::mpsc};
+    /// use std::time::Duration;
+    /// let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    /// let transfer = TransferBuilder::new()
+    ///     .stall_threshold(Duration::from_millis(50))
+    ///     .build_from_chunks(receiver, io::sink());
+    /// // Nothing is sent for a while
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert!(transfer.stalled());
+    /// sender.send(vec![0; 1024]).unwrap();
+    /// transfer.wait_for_transferred(1024);
+    /// assert!(!transfer.stalled());
+    /// ```
+    pub fn stalled(&self) -> bool {
+        self.state.is_stalled(self.config.stall_threshold)
+    }
+
+    /// Blocks until at least `threshold` bytes have been transferred, or the transfer completes.
+    ///
+    /// Returns `true` if the threshold was reached, or `false` if the transfer completed (either
+    /// successfully or with an error) before reaching it.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 4096]), io::sink());
+    /// assert!(transfer.wait_for_transferred(1024));
+    /// assert!(transfer.transferred() >= 1024);
+    /// // The transfer ends before 8 KiB are transferred
+    /// assert!(!transfer.wait_for_transferred(8192));
+    /// assert!(transfer.is_complete());
+    /// ```
+    pub fn wait_for_transferred(&self, threshold: u64) -> bool {
+        self.state.wait_for_transferred(threshold)
+    }
+
+    /// Blocks until the transfer is complete, without consuming the `Transfer`.
+    ///
+    /// This returns as soon as the transfer ends, however it ended; call [`finish`][Self::finish]
+    /// to find out whether it succeeded.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 4096]), io::sink());
+    /// transfer.wait();
+    /// assert!(transfer.is_complete());
+    /// assert_eq!(transfer.transferred(), 4096);
+    /// ```
+    pub fn wait(&self) {
+        self.state.wait_complete();
+    }
+
+    /// Blocks until the transfer is complete, or `timeout` elapses, without consuming the
+    /// `Transfer`. Returns whether the transfer is complete.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// use std::time::Duration;
+    /// // A reader which never ends
+    /// let transfer = Transfer::new(io::repeat(0), io::sink());
+    /// assert!(!transfer.wait_timeout(Duration::from_millis(10)));
+    /// transfer.cancel();
+    /// assert!(transfer.wait_timeout(Duration::from_secs(60)));
+    /// ```
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.state.wait_complete_timeout(timeout)
+    }
+
+    /// Prints the string returned by `status` every `interval`, on the same line of the terminal,
+    /// until the transfer completes, then moves onto a new line.
+    ///
+    /// `status` is passed a snapshot of the transfer's statistics, and runs on a separate thread,
+    /// so this returns straight away, and replaces the usual loop checking
+    /// [`is_complete`][Transfer::is_complete] and sleeping. The status is printed one final time
+    /// once the transfer is complete, before [`finish`][Transfer::finish] returns. If the
+    /// `Transfer` is dropped first, printing stops.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// transfer.print_every(Duration::from_secs(1), |stats| {
+    ///     format!("{} bytes transferred", stats.transferred)
+    /// });
+    /// let (reader, writer) = transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    /// Printing doesn't block, so a transfer which never ends can still be cancelled:
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// use std::time::Duration;
+    /// let transfer = Transfer::new(io::repeat(0), io::sink());
+    /// transfer.print_every(Duration::from_millis(10), |stats| {
+    ///     format!("{} bytes transferred", stats.transferred)
+    /// });
+    /// transfer.cancel();
+    /// assert!(transfer.finish_or_cancelled()?.is_cancelled());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn print_every<F>(&self, interval: Duration, status: F)
+    where
+        F: FnMut(&ProgressSnapshot) -> String + Send + 'static,
+    {
+        let printer = print_every(
+            Arc::clone(&self.state),
+            Arc::downgrade(&self.printers),
+            interval,
+            status,
+        );
+        self.printers.lock().unwrap().push(printer);
+    }
+
+    /// Returns the time between the transfer being created and the first bytes being read from
+    /// the reader, or `None` if nothing has been read yet, or timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    ///
+    /// For a download, this is the time spent waiting for the server to respond, which is separate
+    /// from how fast the data arrives once it starts.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// use std::net::TcpStream;
+    /// let reader = TcpStream::connect("example.com:9000")?;
+    /// let writer = File::create("download.bin")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {
+    ///     match transfer.time_to_first_byte() {
+    ///         None => println!("Waiting for server..."),
+    ///         Some(_) => println!("{} bytes", transfer.transferred()),
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn time_to_first_byte(&self) -> Option<Duration> {
+        self.state.time_to_first_byte()
+    }
+
+    /// Returns the number of times the writer has been flushed because too many bytes had been
+    /// written to it without flushing, as configured by
+    /// [`max_in_flight`][TransferBuilder::max_in_flight].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = BufWriter::new(File::create("file2.txt")?);
+    /// let transfer = TransferBuilder::new()
+    ///     .max_in_flight(1024 * 1024)
+    ///     .build(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// println!("Flushed {} times", transfer.forced_flushes());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn forced_flushes(&self) -> u64 {
+        self.state.forced_flushes.load(Ordering::Acquire)
+    }
+
+    /// Returns whether the transfer used an optimised copy, such as a kernel zero-copy path, rather
+    /// than reading into a buffer and writing it out, or `None` if it isn't complete yet.
+    ///
+    /// Transfers only copy inside the kernel if [`zero_copy`][TransferBuilder::zero_copy] is
+    /// enabled, and it's possible for their reader and writer.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// while !transfer.is_complete() {}
+    /// assert_eq!(transfer.used_fast_path(), Some(false));
+    /// ```
+    pub fn used_fast_path(&self) -> Option<bool> {
+        if self.is_complete() {
+            Some(self.state.used_fast_path.load(Ordering::Acquire))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of chunks which have been read, but are waiting to be written, in a
+    /// transfer which is [pipelined][TransferBuilder::pipeline]. This is always 0 for other
+    /// transfers, and once the transfer is complete.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::net::TcpStream;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = TcpStream::connect("example.com:9000")?;
+    /// let transfer = TransferBuilder::new().pipeline(8).build(reader, writer);
+    /// while !transfer.is_complete() {
+    ///     match transfer.queue_depth() {
+    ///         0 => println!("Waiting for the file"),
+    ///         8 => println!("Waiting for the network"),
+    ///         _ => {}
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn queue_depth(&self) -> u64 {
+        self.state.queued.load(Ordering::Acquire)
+    }
+
+    /// Returns the most bytes which have ever been read, but were waiting to be written, at once,
+    /// in a transfer which is [pipelined][TransferBuilder::pipeline]. This is always 0 for other
+    /// transfers.
+    ///
+    /// This helps to choose the [buffer size][TransferBuilder::buffer_size] and pipeline depth: if
+    /// it stays well below their product, the reader can't keep up with the writer, and a smaller
+    /// queue would do.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let reader = io::Cursor::new(vec![0; 64 * 1024]);
+    /// let transfer = TransferBuilder::new()
+    ///     .buffer_size(1024)
+    ///     .pipeline(4)
+    ///     .build(reader, io::sink());
+    /// transfer.wait();
+    /// // Every chunk read is a full 1 KiB
+    /// assert!(transfer.buffer_high_water() >= 1024);
+    ///
+    /// let unpipelined = TransferBuilder::new().build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// unpipelined.wait();
+    /// assert_eq!(unpipelined.buffer_high_water(), 0);
+    /// ```
+    pub fn buffer_high_water(&self) -> u64 {
+        self.state.queue_high_water.load(Ordering::Acquire)
+    }
+
+    /// Returns a snapshot of the transfer's statistics right now.
+    ///
+    /// Unlike calling [`transferred`][Self::transferred], [`speed`][Self::speed] and so on one
+    /// after another, the statistics in a snapshot are all taken at the same moment, so they're
+    /// always consistent with each other.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// let stats = transfer.snapshot();
+    /// println!("{} bytes at {} B/s", stats.transferred, stats.speed);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// Snapshots say which transfer they were taken from:
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .name("backup")
+    ///     .build(io::empty(), io::sink());
+    /// let stats = transfer.snapshot();
+    /// assert_eq!(stats.id, transfer.id());
+    /// assert_eq!(stats.name.as_deref(), Some("backup"));
+    /// ```
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        self.state.snapshot()
+    }
+
+    /// Returns the transfer's statistics right now as labelled fields with a stable schema, for
+    /// consumption by other programs. See [`ProgressSnapshot::fields`] for the schema.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    ///     let line: Vec<String> = transfer
+    ///         .progress_fields()
+    ///         .map(|(name, value)| format!("{}={}", name, value))
+    ///         .collect();
+    ///     println!("{}", line.join(" "));
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn progress_fields(&self) -> impl Iterator<Item = (&'static str, String)> {
+        self.snapshot().fields()
+    }
+
+    /// Returns the number of chunks transferred thus far, I.E. how many times a buffer has been
+    /// read from the reader and written to the writer.
+    ///
+    /// Together with [`transferred`][Transfer::transferred], this shows the
+    /// [average size][Transfer::average_chunk_size] of the chunks being read, which can help with
+    /// tuning the transfer.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// println!(
+    /// "Transferred {} bytes in {} chunks",
+    /// transfer.transferred(),
+    /// transfer.chunks()
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn chunks(&self) -> u64 {
+        self.state.chunks.load(Ordering::Acquire)
+    }
+
+    /// Returns the average number of bytes transferred per [chunk][Transfer::chunks], or `None` if
+    /// no chunks have been transferred yet.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 16 * 1024]), io::sink());
+    /// while !transfer.is_complete() {}
+    /// // Chunks are read into an 8 KiB buffer
+    /// assert_eq!(transfer.average_chunk_size(), Some(8 * 1024));
+    /// ```
+    pub fn average_chunk_size(&self) -> Option<u64> {
+        self.state.average_chunk_size()
+    }
+
+    /// Returns the length of the largest chunk read from the reader, or `None` if no chunks have
+    /// been read yet.
+    ///
+    /// This is at most the [buffer size][TransferBuilder::buffer_size], so a maximum well below it
+    /// means the reader only ever returns small chunks, which can slow the transfer down. Transfers
+    /// copying [inside the kernel][TransferBuilder::zero_copy] don't use the buffer, and copy up to
+    /// 1 MiB at a time instead.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, Read};
+    /// // Reads at most 512 bytes at a time
+    /// let reader = io::Cursor::new(vec![0; 4096]).chain(io::Cursor::new(vec![0; 100]));
+    /// let transfer = TransferBuilder::new()
+    ///     .buffer_size(512)
+    ///     .build(reader, io::sink());
+    /// transfer.wait();
+    /// assert_eq!(transfer.max_chunk_size(), Some(512));
+    /// assert_eq!(transfer.chunks(), 9);
+    /// ```
+    pub fn max_chunk_size(&self) -> Option<u64> {
+        self.state.max_chunk_size()
+    }
+
+    /// Returns the elapsed time since the transfer started, or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    ///
+    /// Once the transfer is complete, this is the time it took, and no longer changes.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// println!("Transfer took {:?}", transfer.running_time());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::{io, thread, time::Duration};
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// let running_time = transfer.running_time();
+    /// thread::sleep(Duration::from_millis(10));
+    /// assert_eq!(transfer.running_time(), running_time);
+    /// ```
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the [running time][Transfer::running_time] formatted for humans, like "3m 41s".
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .track_timing(false)
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// assert_eq!(transfer.running_time_string(), "0s");
+    /// ```
+    pub fn running_time_string(&self) -> String {
+        format::duration(self.running_time())
+    }
+
+    /// Returns the total time the transfer has spent blocked reading from the reader, or zero if
+    /// timing isn't being [tracked][TransferBuilder::track_timing].
+    ///
+    /// Compared with [`write_wait`][Transfer::write_wait], this shows whether the reader or the
+    /// writer is holding the transfer up. In a [pipelined][TransferBuilder::pipeline] transfer,
+    /// this is the time spent waiting for the reading thread to provide data.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// use std::net::TcpStream;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = TcpStream::connect("example.com:9000")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// transfer.wait();
+    /// if transfer.read_wait() > transfer.write_wait() {
+    ///     println!("The disk is the bottleneck");
+    /// } else {
+    ///     println!("The network is the bottleneck");
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn read_wait(&self) -> Duration {
+        self.state
+            .read_write_time()
+            .map_or(Duration::ZERO, |(reading, _)| reading)
+    }
+
+    /// Returns the total time the transfer has spent blocked writing to the writer, excluding
+    /// [flushing][Transfer::flush_time], or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io;
+    /// let transfer = TransferBuilder::new()
+    ///     .track_timing(false)
+    ///     .build(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// assert_eq!(transfer.write_wait(), std::time::Duration::ZERO);
+    /// ```
+    pub fn write_wait(&self) -> Duration {
+        self.state
+            .read_write_time()
+            .map_or(Duration::ZERO, |(_, writing)| writing)
+    }
+
+    /// Returns a history of the transfer's progress, as `(time, bytes transferred)` samples, oldest
+    /// first, where the time is measured from when the transfer was created. It's empty if timing
+    /// isn't being [tracked][TransferBuilder::track_timing].
+    ///
+    /// By default, a sample is taken at most once a second, and only the last 60 are kept, as
+    /// configured by [`speed_history`][TransferBuilder::speed_history]. Samples are only taken as
+    /// data is transferred, so a gap between two samples means the transfer was stalled or paused.
+    /// The speed between two samples is the difference in bytes divided by the difference in time,
+    /// which is enough to draw a graph of the transfer's throughput.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// transfer.wait();
+    /// let history = transfer.speed_history();
+    /// // The first sample is taken when the transfer is created
+    /// assert_eq!(history[0], (std::time::Duration::ZERO, 0));
+    /// let speeds: Vec<f64> = history
+    ///     .windows(2)
+    ///     .map(|pair| (pair[1].1 - pair[0].1) as f64 / (pair[1].0 - pair[0].0).as_secs_f64())
+    ///     .collect();
+    /// ```
+    pub fn speed_history(&self) -> Vec<(Duration, u64)> {
+        self.state.speed_history()
+    }
+
+    /// Returns the ratio of the time the transfer has spent reading to the time it has spent
+    /// writing, or `None` if timing isn't being [tracked][TransferBuilder::track_timing] or
+    /// nothing has been written yet.
+    ///
+    /// A ratio well above 1 means the reader is the bottleneck, while one well below 1 means the
+    /// writer is.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// if let Some(ratio) = transfer.read_write_time_ratio() {
+    ///     println!("Spent {:.2} times as long reading as writing", ratio);
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn read_write_time_ratio(&self) -> Option<f64> {
+        let (reading, writing) = self.state.read_write_time()?;
+        if writing.is_zero() {
+            return None;
+        }
+        Some(reading.as_secs_f64() / writing.as_secs_f64())
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer while it was actually
+    /// reading or writing, or 0 if timing isn't being [tracked][TransferBuilder::track_timing].
+    ///
+    /// Unlike [`speed`][Transfer::speed], this excludes time spent flushing the writer, waiting
+    /// for a [bandwidth limiter][TransferBuilder::bandwidth_limiter] or paused outside a
+    /// [schedule][TransferBuilder::schedule], so it reflects how fast the reader and writer
+    /// themselves are. The time spent flushing is returned by
+    /// [`flush_time`][Transfer::flush_time].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::TransferBuilder;
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = BufWriter::new(File::create("file2.txt")?);
+    /// let transfer = TransferBuilder::new()
+    ///     .max_in_flight(1024 * 1024)
+    ///     .build(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// println!(
+    ///     "{} B/s overall, {} B/s excluding {:?} spent flushing",
+    ///     transfer.speed(),
+    ///     transfer.transfer_speed(),
+    ///     transfer.flush_time()
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn transfer_speed(&self) -> u64 {
+        match self.state.read_write_time() {
+            Some((reading, writing)) if !(reading + writing).is_zero() => {
+                let transferred = self.state.transferred_since_start();
+                (transferred as f64 / (reading + writing).as_secs_f64()).round() as u64
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the total time spent flushing the writer, or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    ///
+    /// This includes flushes forced by [`max_in_flight`][TransferBuilder::max_in_flight], but not
+    /// those made by [`idle_flush`][TransferBuilder::idle_flush], which only happen while no data
+    /// is moving anyway.
+    pub fn flush_time(&self) -> Duration {
+        self.state.flush_time()
+    }
+
+    /// Resets the transfer's statistics without interrupting it, so that they are measured from
+    /// now on, as if the transfer had just started.
+    ///
+    /// The number of bytes [transferred][Transfer::transferred], [chunks][Transfer::chunks] and
+    /// [forced flushes][Transfer::forced_flushes], the [running time][Transfer::running_time],
+    /// the time spent [reading and writing][Transfer::read_write_time_ratio] and the samples used
+    /// for the [windowed speed][Transfer::speed_blended] are all zeroed. This is useful for
+    /// measuring the speed of a long-lived stream over successive intervals.
+    ///
+    /// For a [`SizedTransfer`], the size is unchanged, so afterwards,
+    /// [`fraction_transferred`][SizedTransfer::fraction_transferred],
+    /// [`remaining`][SizedTransfer::remaining] and [`eta`][SizedTransfer::eta] treat the bytes
+    /// transferred since the reset as if they were the whole of the transfer so far.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// use std::time::Duration;
+    /// let transfer = Transfer::new(io::stdin(), io::sink());
+    /// while !transfer.is_complete() {
+    ///     std::thread::sleep(Duration::from_secs(10));
+    ///     println!("{} B/s over the last 10 seconds", transfer.speed());
+    ///     transfer.reset_stats();
+    /// }
+    /// ```
+    pub fn reset_stats(&self) {
+        self.state.reset();
+    }
+
+    /// Returns the elapsed time since the transfer started, excluding any time spent paused outside
+    /// its [schedule][TransferBuilder::schedule], or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{TimeWindow, TransferBuilder};
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .schedule(TimeWindow::hours(1, 5))
+    ///     .build(reader, writer);
+    /// while !transfer.is_complete() {}
+    /// println!(
+    ///     "Transfer took {:?}, of which {:?} was spent copying",
+    ///     transfer.running_time(),
+    ///     transfer.active_time()
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn active_time(&self) -> Duration {
+        self.state.active_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    ///
+    /// Once the transfer is complete, this is its final average speed, and no longer changes.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {
+    /// println!("{}B/s", transfer.speed());
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns the average [speed][Transfer::speed] of the transfer in bits per second, as
+    /// network speeds are usually given.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// use std::net::TcpStream;
+    /// let reader = TcpStream::connect("example.com:9000")?;
+    /// let writer = File::create("download.bin")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {
+    ///     println!("{:.1} Mbit/s", transfer.speed_bits() as f64 / 1e6);
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn speed_bits(&self) -> u64 {
+        self.speed().saturating_mul(8)
+    }
+
+    /// Returns the speed, in bytes per second, of the transfer over the last few seconds, or 0 if
+    /// timing isn't being [tracked][TransferBuilder::track_timing].
+    ///
+    /// Unlike [`speed`][Transfer::speed], which is averaged over the whole transfer, this follows
+    /// changes in throughput, such as a network connection slowing down. The length of the window
+    /// it's measured over is set by [`TransferBuilder::speed_window`].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {
+    ///     println!("{}B/s", transfer.current_speed());
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn current_speed(&self) -> u64 {
+        self.state.windowed_speed().round() as u64
+    }
+
+    /// Returns a blend, in bytes per second, of the [average][Transfer::speed] speed of the
+    /// transfer and its [speed over the last few seconds][Transfer::current_speed] (the windowed
+    /// speed).
+    ///
+    /// `alpha` (clamped between 0.0 and 1.0) is the weight given to the windowed speed once a
+    /// full window of samples is available; the remainder goes to the average speed. Before then,
+    /// the windowed speed's weight is scaled down in proportion to how much of the window has
+    /// elapsed, so the stable average dominates early on while the windowed speed is still noisy.
+    /// An `alpha` of 0.0 always yields the average speed, and 1.0 eventually yields the windowed
+    /// speed.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// while !transfer.is_complete() {
+    /// println!("{}B/s", transfer.speed_blended(0.7));
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn speed_blended(&self, alpha: f64) -> u64 {
+        self.blended_speed(alpha).round() as u64
+    }
+
+    /// Changes the speed limit of the transfer to `bytes_per_sec` bytes per second, taking effect
+    /// immediately.
+    ///
+    /// This sets the rate of the transfer's [`BandwidthLimiter`], so if the limiter is shared with
+    /// other transfers, their combined limit changes too. A limit can only be changed, not added
+    /// or removed, while the transfer is running: if the transfer was created without one, this
+    /// does nothing and returns `false`.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is 0.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, Read};
+    /// let transfer = TransferBuilder::new()
+    ///     .rate_limit(1024)
+    ///     .build(io::repeat(0).take(1024 * 1024), io::sink());
+    /// // Speed things up
+    /// assert!(transfer.set_rate_limit(1024 * 1024 * 1024));
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_rate_limit(&self, bytes_per_sec: u64) -> bool {
+        match &self.config.bandwidth_limiter {
+            Some(limiter) => {
+                limiter.set_rate(bytes_per_sec);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the fraction of its [`BandwidthLimiter`]'s rate that this transfer is currently
+    /// using, based on its speed over the last few seconds, or `None` if it has no limiter.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::{BandwidthLimiter, TransferBuilder};
+    /// use std::fs::File;
+    /// use std::sync::Arc;
+    /// let limiter = Arc::new(BandwidthLimiter::new(1024 * 1024)); // 1 MiB/s
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = TransferBuilder::new()
+    ///     .bandwidth_limiter(limiter)
+    ///     .build(reader, writer);
+    /// while !transfer.is_complete() {
+    /// if let Some(usage) = transfer.bandwidth_usage() {
+    /// println!("Using {:.0}% of the bandwidth budget", usage * 100.0);
+    /// }
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn bandwidth_usage(&self) -> Option<f64> {
+        let limiter = self.config.bandwidth_limiter.as_ref()?;
+        Some(self.state.windowed_speed() / limiter.rate() as f64)
+    }
+
+    fn blended_speed(&self, alpha: f64) -> f64 {
+        if !self.state.is_timed() {
+            return 0.0;
+        }
+        let elapsed = self.running_time().as_secs_f64();
+        let average = self.state.speed();
+        let window_fill = (elapsed / self.state.speed_window().as_secs_f64()).min(1.0);
+        let weight = alpha.clamp(0.0, 1.0) * window_fill;
+        weight * self.state.windowed_speed() + (1.0 - weight) * average
+    }
+}
+
+impl<T, C, W> Transfer<ProgressReader<T, C>, W>
+where
+    T: Read + Send + 'static,
+    C: FnMut(usize) + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `Transfer` from a reader that's already wrapped in a
+    /// [`ProgressReader`], so that it doesn't get wrapped twice.
+    ///
+    /// `make_reader` is called on the transfer's thread, before the transfer starts, with a
+    /// [`ProgressCounter`] for the new transfer. It must return the `ProgressReader`, whose
+    /// callback must pass every byte count it receives to [`ProgressCounter::add`] exactly once.
+    /// Otherwise, the transfer's statistics will be wrong.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use progress_streams::ProgressReader;
+    /// use std::io;
+    /// let transfer = Transfer::from_progress_reader(
+    ///     |counter| {
+    ///         ProgressReader::new(io::Cursor::new(vec![0; 1024]), move |bytes| {
+    ///             // Whatever else the callback needs to do...
+    ///             counter.add(bytes);
+    ///         })
+    ///     },
+    ///     io::sink(),
+    /// );
+    /// let (reader, writer) = transfer.finish()?;
+    /// assert_eq!(reader.into_inner().position(), 1024);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn from_progress_reader<F>(make_reader: F, writer: W) -> Self
+    where
+        F: FnOnce(ProgressCounter) -> ProgressReader<T, C> + Send + 'static,
+    {
+        TransferBuilder::new().build_from_progress_reader(make_reader, writer)
+    }
+}
+
+impl<W> Transfer<Chunks, W>
+where
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `Transfer`, which writes each chunk of bytes received from
+    /// `receiver` to `writer` as it arrives, until the channel is closed.
+    ///
+    /// There is no backpressure: chunks are written as fast as they arrive, and if they arrive
+    /// faster than they can be written, they queue up in the channel. Use a
+    /// [`sync_channel`][mpsc::sync_channel] (with [`SyncSender`][mpsc::SyncSender] sending into
+    /// an ordinary [`Receiver`][mpsc::Receiver]) to bound the queue instead. When the transfer is
+    /// finished, the channel can be recovered with [`Chunks::into_inner`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::sync::mpsc;
+    /// let (sender, receiver) = mpsc::channel();
+    /// let transfer = Transfer::from_chunks(receiver, Vec::new());
+    /// sender.send(b"Hello, ".to_vec()).unwrap();
+    /// sender.send(b"world!".to_vec()).unwrap();
+    /// // Closing the channel ends the transfer
+    /// drop(sender);
+    /// let (_, writer) = transfer.finish()?;
+    /// assert_eq!(writer, b"Hello, world!");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn from_chunks(receiver: mpsc::Receiver<Vec<u8>>, writer: W) -> Self {
+        TransferBuilder::new().build_from_chunks(receiver, writer)
+    }
+}
+
+impl<R, N, W> Transfer<Sequence<R, N>, W>
+where
+    R: Read + Send + 'static,
+    N: Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `Transfer`, which copies each of `items`, a list of readers and
+    /// their names, to `writer` in turn, counting the bytes of all of them together.
+    ///
+    /// `on_item_complete` is called on the transfer's thread with the name of each item once it
+    /// has been entirely read and written. When the transfer is finished, the readers can be
+    /// recovered with [`Sequence::into_inner`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let items = vec![
+    ///     (io::Cursor::new(b"first, ".to_vec()), "first.txt"),
+    ///     (io::Cursor::new(b"second".to_vec()), "second.txt"),
+    /// ];
+    /// let transfer = Transfer::sequence(items, Vec::new(), |name| {
+    ///     println!("Extracted {}", name);
+    /// });
+    /// let (sequence, writer) = transfer.finish()?;
+    /// assert_eq!(writer, b"first, second");
+    /// assert_eq!(sequence.into_inner().len(), 2);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn sequence<F>(items: Vec<(R, N)>, writer: W, on_item_complete: F) -> Self
+    where
+        F: FnMut(&N) + Send + 'static,
+    {
+        TransferBuilder::new().build_sequence(items, writer, on_item_complete)
     }
 
-    /// Returns the elapsed time since the transfer started.
+    /// Returns the index of the item currently being read, and the number of bytes transferred
+    /// from it so far. Once every item has been read, the index is the number of items.
     /// # Example
-    /// ```no_run
+    /// ```
     /// use transfer_progress::Transfer;
-    /// use std::fs::File;
-    /// let reader = File::open("file1.txt")?;
-    /// let writer = File::create("file2.txt")?;
-    /// let transfer = Transfer::new(reader, writer);
-    /// while !transfer.is_complete() {}
-    /// println!("Transfer took {:?}", transfer.running_time());
-    /// # Ok::<_, std::io::Error>(())
+    /// use std::io;
+    /// let items = vec![
+    ///     (io::Cursor::new(vec![0; 1024]), "first"),
+    ///     (io::Cursor::new(vec![0; 2048]), "second"),
+    /// ];
+    /// let transfer = Transfer::sequence(items, io::sink(), |_| {});
+    /// transfer.wait();
+    /// assert_eq!(transfer.current_item(), (2, 0));
     /// ```
-    pub fn running_time(&self) -> Duration {
-        self.start_time.elapsed()
+    pub fn current_item(&self) -> (usize, u64) {
+        self.state.segment()
     }
+}
 
-    /// Returns the average speed, in bytes per second, of the transfer.
+impl<R, W> Transfer<Sequence<R, usize>, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `Transfer`, which copies each of `readers` to `writer` in turn,
+    /// for instance to reassemble a download from its parts.
+    ///
+    /// This is like [`sequence`][Self::sequence], with each reader named by its index, so the
+    /// progress of the current part can be seen with [`current_item`][Self::current_item], while
+    /// the transfer as a whole counts the bytes of all of them together.
     /// # Example
-    /// ```no_run
+    /// ```
     /// use transfer_progress::Transfer;
-    /// use std::fs::File;
-    /// let reader = File::open("file1.txt")?;
-    /// let writer = File::create("file2.txt")?;
-    /// let transfer = Transfer::new(reader, writer);
-    /// while !transfer.is_complete() {
-    /// println!("{}B/s", transfer.speed());
-    /// std::thread::sleep(std::time::Duration::from_secs(1));
-    /// }
+    /// use std::io;
+    /// let parts = vec![
+    ///     io::Cursor::new(b"Hello, ".to_vec()),
+    ///     io::Cursor::new(b"world!".to_vec()),
+    /// ];
+    /// let transfer = Transfer::chained(parts, Vec::new());
+    /// let (sequence, writer) = transfer.finish()?;
+    /// assert_eq!(writer, b"Hello, world!");
+    /// assert_eq!(sequence.completed(), 2);
     /// # Ok::<_, std::io::Error>(())
     /// ```
-    pub fn speed(&self) -> u64 {
-        (self.transferred() as f64 / self.running_time().as_secs_f64()).round() as u64
+    pub fn chained(readers: Vec<R>, writer: W) -> Self {
+        let items = readers
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| (r, i))
+            .collect();
+        Self::sequence(items, writer, |_| {})
+    }
+}
+
+/// Counts the bytes transferred by a [`Transfer`] created with
+/// [`from_progress_reader`][Transfer::from_progress_reader].
+#[derive(Clone)]
+pub struct ProgressCounter(Arc<TransferState>);
+
+impl ProgressCounter {
+    /// Records that `bytes` more bytes have been read by the transfer.
+    pub fn add(&self, bytes: usize) {
+        self.0.add_read(bytes as u64);
     }
 }
 
-#[cfg(feature = "bytesize")]
 impl<R, W> fmt::Debug for Transfer<R, W>
 where
     R: Read + Send + 'static,
     W: Write + Send + 'static,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let transferred = ByteSize::b(self.transferred());
-        let speed = ByteSize::b(self.speed());
-        if f.alternate() {
-            // Use SI units
-            write!(
-                f,
-                "{:#} ({:#}/s)",
-                transferred.to_string_as(true),
-                speed.to_string_as(true)
-            )
-        } else {
-            write!(f, "{:#} ({:#}/s)", transferred, speed)
+        let si = !f.alternate();
+        if let Some(name) = self.name() {
+            write!(f, "{}: ", name)?;
         }
+        write!(
+            f,
+            "{} ({}/s)",
+            format::bytes(self.transferred(), si),
+            format::speed(self.speed(), si, self.config().speed_in_bits)
+        )
     }
 }
 
-#[cfg(feature = "bytesize")]
 impl<R, W> fmt::Display for Transfer<R, W>
 where
     R: Read + Send + 'static,
     W: Write + Send + 'static,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match &self.config().display_template {
+            Some(template) => template::write(
+                f,
+                template,
+                &self.snapshot(),
+                self.name(),
+                f.alternate(),
+                self.config().speed_in_bits,
+            ),
+            None => fmt::Debug::fmt(self, f),
+        }
     }
 }
 
+/// Turns the result of a transfer which was cancelled, which still contains the reader and writer,
+/// into a [`TransferError::Cancelled`].
+pub(crate) fn check_cancelled<T>(
+    state: &TransferState,
+    result: Result<T, TransferError>,
+) -> Result<T, TransferError> {
+    match result {
+        Ok(_) if state.cancelled.load(Ordering::Acquire) => Err(TransferError::Cancelled),
+        result => result,
+    }
+}
+
+/// Starts a thread printing the string returned by `status` every `interval`, overwriting the
+/// previous one, until the transfer completes, or `transfer` can no longer be upgraded.
+fn print_every(
+    state: Arc<TransferState>,
+    transfer: Weak<Mutex<Vec<thread::JoinHandle<()>>>>,
+    interval: Duration,
+    mut status: impl FnMut(&ProgressSnapshot) -> String + Send + 'static,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut stdout = io::stdout();
+        loop {
+            let complete = state.wait_complete_timeout(interval);
+            if !complete && transfer.strong_count() == 0 {
+                return;
+            }
+            // Errors writing to stdout aren't the transfer's problem, so ignore them
+            let _ = write!(stdout, "\r{}", status(&state.snapshot()));
+            if complete {
+                let _ = writeln!(stdout);
+                return;
+            }
+            let _ = stdout.flush();
+        }
+    })
+}
+
 /// Monitors the progress of a transfer with a known size.
 pub struct SizedTransfer<R, W>
 where
@@ -207,7 +1757,8 @@ where
     W: Write + Send + 'static,
 {
     inner: Transfer<R, W>,
-    size: u64,
+    /// The last smoothed ETA (in seconds), and when it was calculated.
+    smoothed_eta: Mutex<Option<(Instant, f64)>>,
 }
 
 impl<R, W> SizedTransfer<R, W>
@@ -227,14 +1778,72 @@ where
     /// # Ok::<_, std::io::Error>(())
     /// ```
     pub fn new(reader: R, writer: W, size: u64) -> Self {
+        TransferBuilder::new().build_sized(reader, writer, size)
+    }
+
+    /// Creates and starts a new `SizedTransfer` which scatters the data from the reader into
+    /// `ranges` of the writer, given as `(offset, length)` pairs. The writer is seeked to the
+    /// start of each range in turn, and exactly that many bytes are copied into it from the
+    /// reader, so the reader must supply the data for every range, one after the other.
+    ///
+    /// The size of the transfer is the total length of all the ranges. If the reader ends before
+    /// every range has been filled, the transfer fails with an
+    /// [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error. Any data left in the reader after the
+    /// last range is left unread.
+    /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let reader = io::Cursor::new(b"worldhello ".to_vec());
+    /// let writer = io::Cursor::new(vec![0; 11]);
+    /// let transfer = SizedTransfer::scatter(reader, writer, vec![(6, 5), (0, 6)]);
+    /// assert_eq!(transfer.size(), 11);
+    /// let (_reader, writer) = transfer.finish()?;
+    /// assert_eq!(writer.into_inner(), b"hello world");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn scatter(reader: R, writer: W, ranges: Vec<(u64, u64)>) -> Self
+    where
+        W: Seek,
+    {
+        TransferBuilder::new().build_scatter(reader, writer, ranges)
+    }
+
+    /// Creates and starts a new `SizedTransfer`, counting `already_transferred` bytes of its
+    /// `size` as already transferred, as when resuming an interrupted transfer. The reader and
+    /// writer should already be positioned past the data transferred before.
+    ///
+    /// See [`TransferBuilder::offset`] for details.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::{File, OpenOptions};
+    /// use std::io::{Seek, SeekFrom};
+    /// let mut reader = File::open("file1.txt")?;
+    /// let size = reader.metadata()?.len();
+    /// let writer = OpenOptions::new().append(true).open("file2.txt")?;
+    /// let already_transferred = writer.metadata()?.len();
+    /// reader.seek(SeekFrom::Start(already_transferred))?;
+    /// let transfer = SizedTransfer::with_offset(reader, writer, size, already_transferred);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn with_offset(reader: R, writer: W, size: u64, already_transferred: u64) -> Self {
+        TransferBuilder::new()
+            .offset(already_transferred)
+            .build_sized(reader, writer, size)
+    }
+
+    /// Wraps a `Transfer` whose size is known.
+    pub(crate) fn from_transfer(inner: Transfer<R, W>) -> Self {
+        debug_assert!(inner.state.size().is_some());
         Self {
-            inner: Transfer::new(reader, writer),
-            size,
+            inner,
+            smoothed_eta: Mutex::new(None),
         }
     }
 
     /// Returns the total size (in bytes) of the transfer, as specified when calling
-    /// [`new`][SizedTransfer::new].
+    /// [`new`][SizedTransfer::new], or last [set][SizedTransfer::set_size].
     /// # Example
     /// ```no_run
     /// use transfer_progress::SizedTransfer;
@@ -249,11 +1858,44 @@ where
     /// # }
     /// ```
     pub fn size(&self) -> u64 {
-        self.size
+        self.inner.state.size().unwrap_or_default()
     }
 
-    /// Returns the number of bytes remaining.
+    /// Changes the total size (in bytes) of the transfer, for when the real size only becomes
+    /// known once the transfer has started.
+    ///
+    /// [`remaining`][SizedTransfer::remaining],
+    /// [`fraction_transferred`][SizedTransfer::fraction_transferred] and the ETAs reflect the new
+    /// size straight away. It doesn't affect when the transfer ends, however: if
+    /// [`strict_size`][TransferBuilder::strict_size] is enabled, the original size is still
+    /// enforced, and channels already returned by
+    /// [`percent_channel`][SizedTransfer::percent_channel] keep using the size they started with.
+    ///
+    /// # Panics
+    /// Panics if `size` is less than the number of bytes already transferred.
     /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// // We initially think there's only 1 KiB to transfer
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 4096]), io::sink(), 1024);
+    /// transfer.set_size(4096);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_size(&self, size: u64) {
+        let transferred = self.inner.transferred();
+        assert!(
+            size >= transferred,
+            "cannot set the size of a transfer to {} bytes, when {} bytes were already transferred",
+            size,
+            transferred
+        );
+        self.inner.state.set_size(size);
+    }
+
+    /// Returns the number of bytes remaining.
+    /// # Examples
     /// ```no_run
     /// use transfer_progress::SizedTransfer;
     /// use std::fs::File;
@@ -267,8 +1909,114 @@ where
     /// }
     /// # Ok::<_, std::io::Error>(())
     /// ```
+    ///
+    /// The remaining byte count only ever goes down as the transfer progresses:
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let data = vec![0; 4 * 1024 * 1024];
+    /// let transfer = SizedTransfer::new(io::Cursor::new(data), io::sink(), 4 * 1024 * 1024);
+    /// let mut last = transfer.remaining();
+    /// while !transfer.is_complete() {
+    ///     let remaining = transfer.remaining();
+    ///     assert!(remaining <= last);
+    ///     last = remaining;
+    /// }
+    /// assert_eq!(transfer.remaining(), 0);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
     pub fn remaining(&self) -> u64 {
-        self.size - self.inner.transferred()
+        // Load the size only once, since it may change in the meantime
+        let size = self.size();
+        size.saturating_sub(self.clamped_transferred(size))
+    }
+
+    /// Returns a channel which receives the percentage of the transfer that is complete, from 0 to
+    /// 100, each time it changes.
+    ///
+    /// The percentages are rounded down, and sent by a separate thread, so a consumer receives at
+    /// most 101 messages, in increasing order. Once the transfer completes successfully, 100 is
+    /// always sent, even if the last chunk skipped over several percentages. If the transfer
+    /// fails, the channel is closed without sending 100.
+    /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let data = vec![0; 1024 * 1024];
+    /// let transfer = SizedTransfer::new(io::Cursor::new(data), io::sink(), 1024 * 1024);
+    /// let percentages: Vec<u8> = transfer.percent_channel().iter().collect();
+    /// assert!(percentages.windows(2).all(|pair| pair[0] < pair[1]));
+    /// assert_eq!(percentages.last(), Some(&100));
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn percent_channel(&self) -> mpsc::Receiver<u8> {
+        let (sender, receiver) = mpsc::channel();
+        let state = Arc::clone(&self.inner.state);
+        let size = self.size();
+        thread::spawn(move || {
+            let percent_of = |transferred: u64| {
+                if size == 0 {
+                    100
+                } else {
+                    (u128::from(transferred) * 100 / u128::from(size)).min(100) as u8
+                }
+            };
+            // Check completion first, so we don't miss bytes transferred just before completing
+            let mut complete = state.is_complete();
+            let mut percent = percent_of(state.transferred());
+            let mut last_sent = None;
+            loop {
+                if complete && percent < 100 {
+                    if state.failed.load(Ordering::Acquire) {
+                        return;
+                    }
+                    percent = 100;
+                }
+                if last_sent != Some(percent) {
+                    if sender.send(percent).is_err() {
+                        // Nobody's listening any more
+                        return;
+                    }
+                    last_sent = Some(percent);
+                }
+                if percent == 100 || complete {
+                    return;
+                }
+                // The fewest bytes needed to reach the next percentage
+                let next = (u128::from(size) * (u128::from(percent) + 1)).div_ceil(100) as u64;
+                complete = !state.wait_for_transferred(next);
+                percent = percent_of(state.transferred());
+            }
+        });
+        receiver
+    }
+
+    /// Tests if more bytes have been transferred than the size of the transfer, because the reader
+    /// had more data than expected.
+    ///
+    /// When this happens, [`remaining`][Self::remaining] stays at 0, and
+    /// [`fraction_transferred`][Self::fraction_transferred] at 1.0. To fail the transfer instead,
+    /// enable [`strict_size`][TransferBuilder::strict_size].
+    /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 2000]), io::sink(), 1024);
+    /// transfer.wait();
+    /// assert!(transfer.overshot());
+    /// assert_eq!(transfer.remaining(), 0);
+    /// assert_eq!(transfer.fraction_transferred(), 1.0);
+    /// ```
+    pub fn overshot(&self) -> bool {
+        self.inner.transferred() > self.size()
+    }
+
+    /// Returns the number of bytes transferred, capped at `size` in case the transfer
+    /// [overshot][Self::overshot].
+    fn clamped_transferred(&self, size: u64) -> u64 {
+        self.inner.transferred().min(size)
     }
 
     /// Consumes the `SizedTransfer`, blocking until the transfer is complete.
@@ -292,6 +2040,122 @@ where
         self.inner.finish()
     }
 
+    /// Returns the result of the transfer, like [`finish`][Self::finish], if it's complete, or
+    /// gives back the `SizedTransfer` if it's still running, without blocking.
+    // The signature mirrors `finish`, handing back the transfer itself if it isn't complete
+    #[allow(clippy::result_large_err)]
+    pub fn try_finish(self) -> Result<io::Result<(R, W)>, Self> {
+        if self.inner.handle.is_finished() {
+            Ok(self.finish())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Consumes the `SizedTransfer`, letting it carry on in the background, and returns a
+    /// [`ProgressHandle`] for monitoring it, as for [`Transfer::detach`].
+    pub fn detach(self) -> ProgressHandle {
+        self.inner.detach()
+    }
+
+    /// Consumes the `SizedTransfer`, blocking until the transfer is complete, and returns its
+    /// result along with a snapshot of its final statistics.
+    ///
+    /// The snapshot is returned whether or not the transfer was successful, so, for instance, the
+    /// number of bytes transferred before an error can be logged. Unlike
+    /// [`finish`][Self::finish], if the transfer's thread panicked, this returns
+    /// [`TransferError::Panicked`] rather than panicking.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// let (result, stats) = transfer.finish_with_stats();
+    /// if let Err(e) = result {
+    /// eprintln!("Transfer failed after {} of {} bytes: {}", stats.transferred, 1024, e);
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn finish_with_stats(self) -> (Result<(R, W), TransferError>, ProgressSnapshot) {
+        self.inner.finish_with_stats()
+    }
+
+    /// Returns the number of bytes transferred out of the total, formatted for humans in IEC units
+    /// (multiples of 1024), like "450.2 MiB / 1.0 GiB".
+    ///
+    /// Without the `bytesize` or `humansize` feature, the exact numbers of bytes are used instead.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    ///     println!("{}", transfer.progress_string());
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn progress_string(&self) -> String {
+        self.format_progress(false)
+    }
+
+    /// Like [`progress_string`][SizedTransfer::progress_string], but in SI units (multiples of
+    /// 1000), like "450.2 MB / 1.0 GB".
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1000); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1000);
+    /// while !transfer.is_complete() {
+    ///     println!("{}", transfer.progress_string_si());
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn progress_string_si(&self) -> String {
+        self.format_progress(true)
+    }
+
+    fn format_progress(&self, si: bool) -> String {
+        format!(
+            "{} / {}",
+            format::bytes(self.inner.transferred(), si),
+            format::bytes(self.size(), si)
+        )
+    }
+
+    /// Like [`Transfer::print_every`].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// transfer.print_every(Duration::from_secs(1), |stats| {
+    ///     format!("{:.1} %", stats.fraction_transferred().unwrap() * 100.0)
+    /// });
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn print_every<F>(&self, interval: Duration, status: F)
+    where
+        F: FnMut(&ProgressSnapshot) -> String + Send + 'static,
+    {
+        self.inner.print_every(interval, status);
+    }
+
     /// Returns a fraction between 0.0 and 1.0 representing the state of the transfer.
     /// # Example
     /// ```no_run
@@ -308,12 +2172,19 @@ where
     /// # Ok::<_, std::io::Error>(())
     /// ```
     pub fn fraction_transferred(&self) -> f64 {
-        self.transferred() as f64 / self.size as f64
+        let size = self.size();
+        if size == 0 {
+            return 1.0;
+        }
+        self.clamped_transferred(size) as f64 / size as f64
     }
 
     /// Returns the approximate remaining time until this transfer completes. Returns `None` if
     /// this cannot be calculated (I.E. no bytes have been transferred yet, so a speed cannot be
-    /// determined).
+    /// determined, or timing isn't being [tracked][TransferBuilder::track_timing]).
+    ///
+    /// If the transfer has a [schedule][TransferBuilder::schedule], the estimate includes the time
+    /// it will spend paused.
     /// # Example
     /// ```no_run
     /// use transfer_progress::SizedTransfer;
@@ -332,17 +2203,199 @@ where
     /// }
     /// # Ok::<_, std::io::Error>(())
     /// ```
+    ///
+    /// Only the bytes copied since the transfer started count towards its speed, so the estimate
+    /// for a transfer resuming from an [offset][TransferBuilder::offset] is accurate:
+    /// ```
+    /// use transfer_progress::TransferBuilder;
+    /// use std::io::{self, Read};
+    /// use std::{thread, time::Duration};
+    /// // Reads 1000 bytes every 10ms
+    /// struct Slow(io::Take<io::Repeat>);
+    /// impl Read for Slow {
+    ///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///         thread::sleep(Duration::from_millis(10));
+    ///         let len = buf.len().min(1000);
+    ///         self.0.read(&mut buf[..len])
+    ///     }
+    /// }
+    /// // Half of the data was copied last time, and the rest takes about 500ms
+    /// let reader = Slow(io::repeat(0).take(50_000));
+    /// let transfer = TransferBuilder::new()
+    ///     .offset(50_000)
+    ///     .build_sized(reader, io::sink(), 100_000);
+    /// while transfer.transferred() < 55_000 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// assert!(transfer.eta().unwrap() > Duration::from_millis(100));
+    /// assert!(transfer.snapshot().eta().unwrap() > Duration::from_millis(100));
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
     pub fn eta(&self) -> Option<Duration> {
-        // Cache this so we don't have to perform an atomic access twice
-        let transferred = self.inner.transferred();
-        if transferred == 0 {
+        // The offset wasn't transferred in the time being measured
+        let transferred = self.inner.state.transferred_since_start();
+        if transferred == 0 || !self.inner.state.is_timed() {
             return None;
         }
-        let remaining = self.size - transferred;
-        let elapsed = self.running_time().as_secs_f64();
+        let remaining = self.remaining();
+        let elapsed = self.active_time().as_secs_f64();
         let eta = (elapsed / transferred as f64) * remaining as f64;
-        Some(Duration::from_secs_f64(eta))
+        Some(self.scheduled(Duration::from_secs_f64(eta)))
+    }
+
+    /// Returns the [ETA][SizedTransfer::eta] formatted for humans, like "3m 41s", or `None` if it
+    /// can't be calculated.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    ///     match transfer.eta_string() {
+    ///         Some(eta) => println!("{} left", eta),
+    ///         None => println!("Calculating..."),
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn eta_string(&self) -> Option<String> {
+        self.eta().map(format::duration)
     }
+
+    /// Like [`eta`][SizedTransfer::eta], but estimates the remaining time using the
+    /// [blended speed][Transfer::speed_blended] with the given `alpha`, rather than the average
+    /// speed. Returns `None` if the blended speed is zero.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    /// if let Some(eta) = transfer.eta_blended(0.7) {
+    /// println!("Transfer will complete in approximately {:?}", eta);
+    /// }
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn eta_blended(&self, alpha: f64) -> Option<Duration> {
+        let speed = self.inner.blended_speed(alpha);
+        if speed <= 0.0 {
+            return None;
+        }
+        let remaining = self.remaining();
+        Some(self.scheduled(Duration::from_secs_f64(remaining as f64 / speed)))
+    }
+
+    /// Converts an estimate of the time left copying into the wall-clock time it will take,
+    /// accounting for any pauses outside the transfer's [schedule][TransferBuilder::schedule].
+    fn scheduled(&self, active: Duration) -> Duration {
+        match &self.config().schedule {
+            Some(schedule) => schedule.wall_time(active, schedule::time_of_day()),
+            None => active,
+        }
+    }
+
+    /// Like [`eta`][SizedTransfer::eta], but smoothed so that it counts down steadily, rather than
+    /// jumping around as the speed of the transfer fluctuates.
+    ///
+    /// Each call counts the previous smoothed estimate down by the time since it was made, then
+    /// moves it towards the current [`eta`][SizedTransfer::eta], more so the longer it has been
+    /// since the last call, as set by [`TransferBuilder::eta_smoothing_time`]. Returns `None`
+    /// whenever [`eta`][SizedTransfer::eta] does.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    /// if let Some(eta) = transfer.eta_smoothed() {
+    /// println!("Transfer will complete in approximately {}s", eta.as_secs());
+    /// }
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn eta_smoothed(&self) -> Option<Duration> {
+        let eta = self.eta()?.as_secs_f64();
+        let now = Instant::now();
+        let mut smoothed_eta = self.smoothed_eta.lock().unwrap();
+        let smoothed = match *smoothed_eta {
+            Some((last_update, last_eta)) => {
+                let elapsed = now.duration_since(last_update).as_secs_f64();
+                let predicted = (last_eta - elapsed).max(0.0);
+                let smoothing_time = self.config().eta_smoothing_time.as_secs_f64();
+                let weight = 1.0 - (-elapsed / smoothing_time).exp();
+                predicted + weight * (eta - predicted)
+            }
+            None => eta,
+        };
+        *smoothed_eta = Some((now, smoothed));
+        Some(Duration::from_secs_f64(smoothed))
+    }
+}
+
+impl<W> SizedTransfer<File, W>
+where
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `SizedTransfer` from `file` to `writer`, with its size set to the
+    /// length of the rest of the file, from its current position.
+    ///
+    /// The transfer stops at that length, even if the file grows while it's being copied.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::from_file(reader, writer)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn from_file(mut file: File, writer: W) -> io::Result<Self> {
+        let size = file
+            .metadata()?
+            .len()
+            .saturating_sub(file.stream_position()?);
+        Ok(TransferBuilder::new()
+            .limit(size)
+            .build_sized(file, writer, size))
+    }
+}
+
+/// Creates and starts a new [`SizedTransfer`] copying the file at `from` to a new file at `to`,
+/// replacing it if it already exists.
+///
+/// The transfer's size is the length of `from`, and it stops there, even if the file grows while
+/// it's being copied. To create any missing parent directories of `to` first, use
+/// [`TransferBuilder::create_dirs`] with [`TransferBuilder::build_path_to_path`].
+/// # Example
+/// ```no_run
+/// let transfer = transfer_progress::copy_path_to_path("file1.txt", "file2.txt")?;
+/// while !transfer.is_complete() {
+///     println!("{:.1}%", transfer.fraction_transferred() * 100.0);
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// transfer.finish()?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub fn copy_path_to_path<P, Q>(from: P, to: Q) -> io::Result<SizedTransfer<File, File>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    TransferBuilder::new().build_path_to_path(from, to)
 }
 
 impl<R, W> std::ops::Deref for SizedTransfer<R, W>
@@ -357,43 +2410,48 @@ where
     }
 }
 
-#[cfg(feature = "bytesize")]
 impl<R, W> fmt::Debug for SizedTransfer<R, W>
 where
     R: Read + Send + 'static,
     W: Write + Send + 'static,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let percentage = self.fraction_transferred() * 100.0;
-        let transferred = ByteSize::b(self.transferred());
-        let size = ByteSize::b(self.size);
-        let speed = ByteSize::b(self.speed());
-        if f.alternate() {
-            write!(
-                f,
-                "{:.1} % ({} of {}, {}/s)",
-                percentage, transferred, size, speed
-            )
-        } else {
-            write!(
-                f,
-                "{:.1} % ({} of {}, {}/s)",
-                percentage,
-                transferred.to_string_as(true),
-                size.to_string_as(true),
-                speed.to_string_as(true)
-            )
+        let si = f.alternate();
+        if let Some(name) = self.name() {
+            write!(f, "{}: ", name)?;
+        }
+        write!(
+            f,
+            "{:.1} % ({} of {}, {}/s, {} elapsed",
+            self.fraction_transferred() * 100.0,
+            format::bytes(self.transferred(), si),
+            format::bytes(self.size(), si),
+            format::speed(self.speed(), si, self.config().speed_in_bits),
+            self.running_time_string()
+        )?;
+        match self.eta_string() {
+            Some(eta) => write!(f, ", ETA {})", eta),
+            None => write!(f, ")"),
         }
     }
 }
 
-#[cfg(feature = "bytesize")]
 impl<R, W> fmt::Display for SizedTransfer<R, W>
 where
     R: Read + Send + 'static,
     W: Write + Send + 'static,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match &self.config().display_template {
+            Some(template) => template::write(
+                f,
+                template,
+                &self.snapshot(),
+                self.name(),
+                f.alternate(),
+                self.config().speed_in_bits,
+            ),
+            None => fmt::Debug::fmt(self, f),
+        }
     }
 }
@@ -0,0 +1,249 @@
+use std::{
+    io::{self, prelude::*, SeekFrom},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::state::TransferState;
+
+/// Caps the combined speed of any number of transfers sharing it.
+///
+/// The limiter is a token bucket, refilled at the configured rate, which transfers draw from
+/// before writing each chunk of data. To keep things fair, no single write may take more than an
+/// equal share of the bucket's capacity among the transfers that are currently running, so a
+/// fast transfer can't starve the others.
+///
+/// Share a limiter between transfers by wrapping it in an [`Arc`] and passing it to
+/// [`TransferBuilder::bandwidth_limiter`][crate::TransferBuilder::bandwidth_limiter]. A single
+/// limiter shared by every transfer a program makes keeps their total within a cap, such as that
+/// of an internet connection.
+/// # Example
+/// ```no_run
+/// use transfer_progress::{BandwidthLimiter, TransferBuilder};
+/// use std::fs::File;
+/// use std::sync::Arc;
+/// // At most 1 MiB/s, across both transfers
+/// let limiter = Arc::new(BandwidthLimiter::new(1024 * 1024));
+/// let transfer1 = TransferBuilder::new()
+///     .bandwidth_limiter(Arc::clone(&limiter))
+///     .build(File::open("file1.txt")?, File::create("file2.txt")?);
+/// let transfer2 = TransferBuilder::new()
+///     .bandwidth_limiter(Arc::clone(&limiter))
+///     .build(File::open("file3.txt")?, File::create("file4.txt")?);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[doc(alias = "RateLimiter")]
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    rate: AtomicU64,
+    bucket: Mutex<Bucket>,
+    active: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    /// Creates a new `BandwidthLimiter`, allowing at most `bytes_per_sec` bytes per second to be
+    /// written by all the transfers sharing it.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is 0.
+    /// # Example
+    /// ```
+    /// use transfer_progress::BandwidthLimiter;
+    /// let limiter = BandwidthLimiter::new(1024 * 1024); // 1 MiB/s
+    /// ```
+    pub fn new(bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "bandwidth limit must be greater than 0");
+        Self {
+            rate: AtomicU64::new(bytes_per_sec),
+            bucket: Mutex::new(Bucket {
+                tokens: 0.0,
+                last_refill: Instant::now(),
+            }),
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum combined speed, in bytes per second, of the transfers sharing this
+    /// limiter.
+    /// # Example
+    /// ```
+    /// use transfer_progress::BandwidthLimiter;
+    /// let limiter = BandwidthLimiter::new(1024 * 1024);
+    /// assert_eq!(limiter.rate(), 1024 * 1024);
+    /// ```
+    pub fn rate(&self) -> u64 {
+        self.rate.load(Ordering::Acquire)
+    }
+
+    /// Changes the maximum combined speed, in bytes per second, of the transfers sharing this
+    /// limiter, taking effect immediately, even for transfers which are already running.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is 0.
+    /// # Example
+    /// ```
+    /// use transfer_progress::BandwidthLimiter;
+    /// let limiter = BandwidthLimiter::new(1024 * 1024);
+    /// limiter.set_rate(512 * 1024);
+    /// assert_eq!(limiter.rate(), 512 * 1024);
+    /// ```
+    pub fn set_rate(&self, bytes_per_sec: u64) {
+        assert!(bytes_per_sec > 0, "bandwidth limit must be greater than 0");
+        self.rate.store(bytes_per_sec, Ordering::Release);
+    }
+
+    /// Returns the number of running transfers sharing this limiter.
+    /// # Example
+    /// ```
+    /// use transfer_progress::BandwidthLimiter;
+    /// let limiter = BandwidthLimiter::new(1024 * 1024);
+    /// assert_eq!(limiter.active_transfers(), 0);
+    /// ```
+    pub fn active_transfers(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// The most tokens the bucket can hold: a tenth of a second's worth, so bursts stay short.
+    fn capacity(rate: f64) -> f64 {
+        (rate / 10.0).max(1.0)
+    }
+
+    /// Blocks until at least one byte may be written, then returns how many of the `wanted` bytes
+    /// may be written.
+    fn acquire(&self, wanted: usize) -> usize {
+        loop {
+            // The rate may change while we wait
+            let rate = self.rate() as f64;
+            let capacity = Self::capacity(rate);
+            let share = (capacity / self.active_transfers().max(1) as f64).max(1.0);
+            let target = (wanted as f64).min(share).floor().max(1.0);
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last_refill).as_secs_f64() * rate;
+            bucket.tokens = (bucket.tokens + refill).min(capacity);
+            bucket.last_refill = now;
+            if bucket.tokens >= target {
+                bucket.tokens -= target;
+                return target as usize;
+            }
+            let wait = (target - bucket.tokens) / rate;
+            drop(bucket);
+            thread::sleep(Duration::from_secs_f64(wait));
+        }
+    }
+
+    /// Gives back `unused` bytes' worth of tokens, which were [acquired][Self::acquire] but not
+    /// written, so they aren't lost to the transfers sharing the limiter.
+    fn release(&self, unused: usize) {
+        if unused == 0 {
+            return;
+        }
+        let capacity = Self::capacity(self.rate() as f64);
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.tokens = (bucket.tokens + unused as f64).min(capacity);
+    }
+}
+
+/// A running transfer's registration with a [`BandwidthLimiter`].
+pub(crate) struct LimiterSlot(Arc<BandwidthLimiter>);
+
+impl LimiterSlot {
+    pub(crate) fn new(limiter: Arc<BandwidthLimiter>) -> Self {
+        limiter.active.fetch_add(1, Ordering::AcqRel);
+        Self(limiter)
+    }
+}
+
+impl Drop for LimiterSlot {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A writer which, if given a [`LimiterSlot`], waits for the limiter's permission before each
+/// write, and flushes whenever `max_in_flight` bytes have been written without flushing.
+pub(crate) struct LimitedWriter<'a, W> {
+    pub(crate) inner: InnerWriter<'a, W>,
+    pub(crate) slot: Option<Arc<LimiterSlot>>,
+    pub(crate) max_in_flight: Option<u64>,
+    /// The number of bytes written since the last flush.
+    pub(crate) unflushed: u64,
+    pub(crate) state: &'a TransferState,
+}
+
+/// The writer a [`LimitedWriter`] writes to, which is only behind a lock when it's shared with a
+/// thread flushing it while the transfer is idle.
+pub(crate) enum InnerWriter<'a, W> {
+    Exclusive(&'a mut W),
+    Shared(&'a Mutex<W>),
+}
+
+impl<W> InnerWriter<'_, W> {
+    /// Calls `f` with the writer, locking it first if it's shared.
+    fn with<T>(&mut self, f: impl FnOnce(&mut W) -> T) -> T {
+        match self {
+            Self::Exclusive(writer) => f(writer),
+            Self::Shared(writer) => f(&mut writer.lock().unwrap()),
+        }
+    }
+}
+
+impl<W: Write> LimitedWriter<'_, W> {
+    /// Flushes the writer if at least `max_in_flight` bytes have been written since it was last
+    /// flushed.
+    pub(crate) fn flush_if_full(&mut self) -> io::Result<()> {
+        if self
+            .max_in_flight
+            .is_some_and(|max_in_flight| self.unflushed >= max_in_flight)
+        {
+            self.flush()?;
+            self.state.forced_flushes.fetch_add(1, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = match &self.slot {
+            Some(slot) if !buf.is_empty() => &buf[..slot.0.acquire(buf.len())],
+            _ => buf,
+        };
+        // Only time the write itself, not waiting for the limiter
+        let inner = &mut self.inner;
+        let result = self
+            .state
+            .time_write(|| inner.with(|writer| writer.write(buf)));
+        if let Some(slot) = &self.slot {
+            // A short or failed write doesn't use up everything it was allowed
+            let written = *result.as_ref().unwrap_or(&0);
+            slot.0.release(buf.len() - written);
+        }
+        let written = result?;
+        self.unflushed += written as u64;
+        self.state.add_written(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.unflushed = 0;
+        let inner = &mut self.inner;
+        self.state.time_flush(|| inner.with(W::flush))
+    }
+}
+
+impl<W: Seek> Seek for LimitedWriter<'_, W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.with(|writer| writer.seek(pos))
+    }
+}
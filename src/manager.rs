@@ -0,0 +1,212 @@
+use std::sync::Mutex;
+
+use crate::{ProgressHandle, TransferId};
+
+/// Keeps track of any number of transfers, providing statistics about them as a whole.
+///
+/// Transfers are registered using their [`ProgressHandle`]s, so they can be of any type, and
+/// remain registered, contributing their final statistics, after they finish. With the `metrics`
+/// feature enabled, the statistics can be exported for Prometheus with `prometheus_metrics`.
+///
+/// A manager can also serve as a registry of transfers, which can be looked up by
+/// [ID][TransferManager::get] or [name][TransferManager::find_by_name], and
+/// [cancelled][TransferManager::cancel_all] together. Since [`new`][TransferManager::new] is a
+/// `const fn`, a manager can be put in a `static` to act as a registry for the whole program.
+/// # Example
+/// ```
+/// use transfer_progress::{Transfer, TransferManager};
+/// use std::io;
+/// let manager = TransferManager::new();
+/// let transfer1 = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+/// let transfer2 = Transfer::new(io::Cursor::new(vec![0; 2048]), Vec::new());
+/// manager.add(transfer1.progress_handle());
+/// manager.add(transfer2.progress_handle());
+/// transfer1.finish()?;
+/// transfer2.finish()?;
+/// assert_eq!(manager.transferred(), 3072);
+/// assert_eq!(manager.completed(), 2);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct TransferManager {
+    handles: Mutex<Vec<ProgressHandle>>,
+}
+
+impl TransferManager {
+    /// Creates a new `TransferManager`, with no transfers registered.
+    pub const fn new() -> Self {
+        Self {
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers a transfer with the manager.
+    pub fn add(&self, handle: ProgressHandle) {
+        self.handles.lock().unwrap().push(handle);
+    }
+
+    /// Returns the number of transfers registered with the manager.
+    pub fn len(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+
+    /// Tests if no transfers are registered with the manager.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the handles of all the transfers registered with the manager, in the order they
+    /// were added, to list them individually.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{TransferBuilder, TransferManager};
+    /// use std::io;
+    /// let manager = TransferManager::new();
+    /// for name in ["photos.tar", "music.tar"] {
+    ///     let transfer = TransferBuilder::new()
+    ///         .name(name)
+    ///         .build(io::empty(), io::sink());
+    ///     manager.add(transfer.detach());
+    /// }
+    /// for handle in manager.handles() {
+    ///     println!("{} {}: {} bytes", handle.id(), handle.name().unwrap(), handle.transferred());
+    /// }
+    /// assert_eq!(manager.handles()[1].name(), Some("music.tar"));
+    /// ```
+    pub fn handles(&self) -> Vec<ProgressHandle> {
+        self.handles.lock().unwrap().clone()
+    }
+
+    /// Returns the handle of the registered transfer with the given `id`, if there is one.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{Transfer, TransferManager};
+    /// use std::io;
+    /// let manager = TransferManager::new();
+    /// let transfer = Transfer::new(io::empty(), io::sink());
+    /// manager.add(transfer.progress_handle());
+    /// assert!(manager.get(transfer.id()).is_some());
+    /// ```
+    pub fn get(&self, id: TransferId) -> Option<ProgressHandle> {
+        self.handles
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|handle| handle.id() == id)
+            .cloned()
+    }
+
+    /// Returns the handles of the registered transfers [named][crate::TransferBuilder::name]
+    /// `name`, in the order they were added. Names needn't be unique, so there may be several.
+    pub fn find_by_name(&self, name: &str) -> Vec<ProgressHandle> {
+        self.handles
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|handle| handle.name() == Some(name))
+            .cloned()
+            .collect()
+    }
+
+    /// [Cancels][ProgressHandle::cancel] every registered transfer which is still running, for
+    /// instance when the program is shutting down.
+    ///
+    /// This only asks the transfers to stop, without waiting for them to do so. Wait until
+    /// [`running`][Self::running] returns 0 to be sure they have.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{Transfer, TransferManager};
+    /// use std::io;
+    /// static REGISTRY: TransferManager = TransferManager::new();
+    /// for _ in 0..3 {
+    ///     // Readers which never end
+    ///     REGISTRY.add(Transfer::new(io::repeat(0), io::sink()).detach());
+    /// }
+    /// REGISTRY.cancel_all();
+    /// while REGISTRY.running() > 0 {
+    ///     std::thread::yield_now();
+    /// }
+    /// assert!(REGISTRY.handles().iter().all(|handle| handle.is_cancelled()));
+    /// ```
+    pub fn cancel_all(&self) {
+        for handle in self.handles.lock().unwrap().iter() {
+            if !handle.is_complete() {
+                handle.cancel();
+            }
+        }
+    }
+
+    /// Returns the total number of bytes transferred by all the transfers.
+    pub fn transferred(&self) -> u64 {
+        self.sum(ProgressHandle::transferred)
+    }
+
+    /// Returns the combined average speed, in bytes per second, of the transfers which are still
+    /// running.
+    pub fn speed(&self) -> u64 {
+        self.sum(|handle| {
+            if handle.is_complete() {
+                0
+            } else {
+                handle.speed()
+            }
+        })
+    }
+
+    /// Returns the fraction of the combined size of all the transfers that has been transferred,
+    /// between 0.0 and 1.0, or `None` if the size of any of them isn't known.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{SizedTransfer, TransferManager};
+    /// use std::io;
+    /// let manager = TransferManager::new();
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+    /// manager.add(transfer.progress_handle());
+    /// transfer.finish()?;
+    /// assert_eq!(manager.fraction_transferred(), Some(1.0));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn fraction_transferred(&self) -> Option<f64> {
+        let handles = self.handles.lock().unwrap();
+        let mut size = 0;
+        let mut transferred = 0;
+        for handle in handles.iter() {
+            let handle_size = handle.size()?;
+            size += handle_size;
+            transferred += handle.transferred().min(handle_size);
+        }
+        if size == 0 {
+            return Some(1.0);
+        }
+        Some(transferred as f64 / size as f64)
+    }
+
+    /// Returns the number of transfers which have completed successfully.
+    pub fn completed(&self) -> usize {
+        self.count(|handle| handle.is_complete() && !handle.is_failed() && !handle.is_cancelled())
+    }
+
+    /// Returns the number of transfers which have failed with an error. Cancelled transfers don't
+    /// count as failed.
+    pub fn failed(&self) -> usize {
+        self.count(ProgressHandle::is_failed)
+    }
+
+    /// Returns the number of transfers which are still running.
+    pub fn running(&self) -> usize {
+        self.count(|handle| !handle.is_complete())
+    }
+
+    fn sum(&self, f: impl Fn(&ProgressHandle) -> u64) -> u64 {
+        self.handles.lock().unwrap().iter().map(f).sum()
+    }
+
+    fn count(&self, f: impl Fn(&ProgressHandle) -> bool) -> usize {
+        self.handles
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&handle| f(handle))
+            .count()
+    }
+}
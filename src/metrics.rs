@@ -0,0 +1,66 @@
+use std::fmt::Write;
+
+use crate::TransferManager;
+
+impl TransferManager {
+    /// Returns the manager's statistics in the Prometheus text exposition format, which is also
+    /// understood by OpenMetrics scrapers, ready to be served from a `/metrics` endpoint.
+    ///
+    /// The metrics are:
+    ///
+    /// | Metric                    | Type    | Value                                           |
+    /// |---------------------------|---------|-------------------------------------------------|
+    /// | `bytes_transferred_total` | counter | The bytes transferred by all the transfers      |
+    /// | `transfer_speed_bytes`    | gauge   | The combined speed of the running transfers     |
+    /// | `transfers_active`        | gauge   | The number of transfers which are still running |
+    ///
+    /// Transfers stay registered after they finish, so the counter never goes down.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{Transfer, TransferManager};
+    /// use std::io;
+    /// let manager = TransferManager::new();
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// manager.add(transfer.progress_handle());
+    /// transfer.finish()?;
+    /// let metrics = manager.prometheus_metrics();
+    /// assert!(metrics.contains("\nbytes_transferred_total 1024\n"));
+    /// assert!(metrics.contains("\ntransfers_active 0\n"));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn prometheus_metrics(&self) -> String {
+        let metrics = [
+            (
+                "bytes_transferred_total",
+                "counter",
+                "Total number of bytes transferred.",
+                self.transferred(),
+            ),
+            (
+                "transfer_speed_bytes",
+                "gauge",
+                "Combined average speed of the running transfers, in bytes per second.",
+                self.speed(),
+            ),
+            (
+                "transfers_active",
+                "gauge",
+                "Number of transfers which are still running.",
+                self.running() as u64,
+            ),
+        ];
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            // Writing to a string can't fail
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n",
+                name = name,
+                help = help,
+                kind = kind,
+                value = value
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,34 @@
+/// How a transfer which may have been cancelled ended, as returned by
+/// [`Transfer::finish_or_cancelled`][crate::Transfer::finish_or_cancelled].
+///
+/// Either way, the reader and writer are returned, so that they can be used or cleaned up.
+#[derive(Debug)]
+pub enum Outcome<R, W> {
+    /// The transfer completed successfully.
+    Complete(R, W),
+    /// The transfer was cancelled before it completed. The reader and writer are as the transfer
+    /// left them, with the reader partially read.
+    Cancelled(R, W),
+}
+
+impl<R, W> Outcome<R, W> {
+    /// Tests if the transfer was cancelled.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+    /// assert!(!transfer.finish_or_cancelled()?.is_cancelled());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled(..))
+    }
+
+    /// Returns the reader and writer, however the transfer ended.
+    pub fn into_inner(self) -> (R, W) {
+        match self {
+            Self::Complete(reader, writer) | Self::Cancelled(reader, writer) => (reader, writer),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    io::{self, prelude::*},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    cancel::CancelOnDrop,
+    check_cancelled, copy,
+    state::{CompleteOnPanic, TransferState},
+    ProgressHandle, TransferBuilder, TransferConfig, TransferError,
+};
+
+/// Monitors the progress of a transfer between a seekable [reader][Read] and [writer][Write] of
+/// known size, which is split into ranges copied at the same time by a pool of worker threads.
+///
+/// Each worker opens its own reader and writer, by calling the functions given to
+/// [`new`][ParallelTransfer::new], seeks both of them to the start of its range, and copies it.
+/// The progress of all the workers is counted together. This can make much better use of fast
+/// disks and networks than copying from start to end on a single thread. To configure the
+/// transfer, use [`TransferBuilder::build_parallel`].
+/// # Example
+/// ```no_run
+/// use transfer_progress::ParallelTransfer;
+/// use std::fs::{File, OpenOptions};
+/// let size = File::open("file1.txt")?.metadata()?.len();
+/// File::create("file2.txt")?.set_len(size)?;
+/// let transfer = ParallelTransfer::new(
+///     || File::open("file1.txt"),
+///     || OpenOptions::new().write(true).open("file2.txt"),
+///     size,
+///     4, // Workers
+/// );
+/// transfer.finish()?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct ParallelTransfer {
+    state: Arc<TransferState>,
+    handle: thread::JoinHandle<Result<(), TransferError>>,
+    /// The thread delivering updates to the [`on_update`][TransferBuilder::on_update] callback.
+    ticker: Option<thread::JoinHandle<()>>,
+    _cancel_on_drop: CancelOnDrop,
+}
+
+impl ParallelTransfer {
+    /// Creates and starts a new `ParallelTransfer` of `size` bytes, split between `workers`
+    /// threads.
+    ///
+    /// `open_reader` and `open_writer` are called on each worker's thread to open a reader and
+    /// writer of its own. Every reader must read the same data, and every writer must write to the
+    /// same destination, which should be at least `size` bytes long, or able to grow when written
+    /// past its end.
+    /// # Panics
+    /// Panics if `workers` is 0.
+    pub fn new<R, W, FR, FW>(open_reader: FR, open_writer: FW, size: u64, workers: usize) -> Self
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+        FR: Fn() -> io::Result<R> + Send + Sync + 'static,
+        FW: Fn() -> io::Result<W> + Send + Sync + 'static,
+    {
+        TransferBuilder::new().build_parallel(open_reader, open_writer, size, workers)
+    }
+
+    pub(crate) fn with_builder<R, W, FR, FW>(
+        mut builder: TransferBuilder,
+        open_reader: FR,
+        open_writer: FW,
+        size: u64,
+        workers: usize,
+    ) -> Self
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+        FR: Fn() -> io::Result<R> + Send + Sync + 'static,
+        FW: Fn() -> io::Result<W> + Send + Sync + 'static,
+    {
+        assert!(workers > 0, "a parallel transfer needs at least one worker");
+        if let Some(option) = unsupported_option(&builder.config) {
+            panic!("parallel transfers don't support {}", option);
+        }
+        builder.size = Some(size);
+        let parts = builder.into_parts();
+        let state = Arc::clone(&parts.state);
+        let options = parts.options;
+        let handle = thread::spawn(move || {
+            let _complete = CompleteOnPanic(&state);
+            let ranges = split(size, workers).collect();
+            let res = copy::copy_parallel(&state, options, open_reader, open_writer, ranges);
+            check_cancelled(&state, res)
+        });
+        Self {
+            _cancel_on_drop: CancelOnDrop::new(&parts.state, parts.config.cancel_on_drop),
+            state: parts.state,
+            handle,
+            ticker: parts.ticker,
+        }
+    }
+
+    /// Consumes the `ParallelTransfer`, blocking until the transfer is complete.
+    ///
+    /// If any of the workers failed, returns the first error. If the transfer was
+    /// [cancelled][Self::cancel], returns [`TransferError::Cancelled`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::ParallelTransfer;
+    /// use std::fs::{self, File, OpenOptions};
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("parallel-finish-from"), dir.join("parallel-finish-to"));
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// fs::write(&from, &data)?;
+    /// File::create(&to)?;
+    /// let (from2, to2) = (from.clone(), to.clone());
+    /// let transfer = ParallelTransfer::new(
+    ///     move || File::open(&from2),
+    ///     move || OpenOptions::new().write(true).open(&to2),
+    ///     data.len() as u64,
+    ///     3,
+    /// );
+    /// transfer.finish()?;
+    /// assert_eq!(fs::read(&to)?, data);
+    /// # fs::remove_file(from)?;
+    /// # fs::remove_file(to)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn finish(self) -> io::Result<()> {
+        let res = self
+            .handle
+            .join()
+            .unwrap_or_else(|payload| Err(TransferError::from_panic(payload)));
+        if let Some(ticker) = self.ticker {
+            let _ = ticker.join();
+        }
+        res.map_err(io::Error::from)
+    }
+
+    /// Asks every worker to stop as soon as possible, after which [`finish`][Self::finish]
+    /// returns [`TransferError::Cancelled`].
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Pauses every worker until the transfer is [resumed][Self::resume], as
+    /// [`Transfer::pause`][crate::Transfer::pause] does.
+    pub fn pause(&self) {
+        self.state.pause();
+    }
+
+    /// Resumes the transfer after it has been [paused][Self::pause].
+    pub fn resume(&self) {
+        self.state.resume();
+    }
+
+    /// Tests if the transfer is [paused][Self::pause].
+    pub fn is_paused(&self) -> bool {
+        self.state.is_paused()
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far by all the workers together.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the size of the transfer.
+    pub fn size(&self) -> u64 {
+        self.state.size().unwrap_or_default()
+    }
+
+    /// Returns the elapsed time since the transfer started.
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the combined average speed of all the workers, in bytes per second.
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring this transfer.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+}
+
+/// Returns the name of an option in `config` which parallel transfers don't support, if any is
+/// set. They have no single stream of data to digest, flush when idle, limit or pipeline.
+fn unsupported_option(config: &TransferConfig) -> Option<&'static str> {
+    #[cfg(feature = "digest")]
+    if config.digest.is_some() {
+        return Some("digests");
+    }
+    if config.idle_flush.is_some() {
+        Some("idle flushing")
+    } else if config.limit.is_some() {
+        Some("limits")
+    } else if config.pipeline.is_some() {
+        Some("pipelining")
+    } else {
+        None
+    }
+}
+
+/// Splits `size` bytes into at most `parts` contiguous `(offset, length)` ranges, as evenly as
+/// possible. There's always at least one range, even if it's empty.
+fn split(size: u64, parts: usize) -> impl Iterator<Item = (u64, u64)> {
+    let parts = (parts as u64).clamp(1, size.max(1));
+    let len = size / parts;
+    let extra = size % parts;
+    (0..parts).map(move |i| {
+        // The first `extra` ranges are one byte longer
+        let offset = i * len + i.min(extra);
+        (offset, len + u64::from(i < extra))
+    })
+}
@@ -0,0 +1,268 @@
+use std::{io::prelude::*, time::Duration};
+
+use crate::{
+    DirTransfer, DuplexTransfer, ParallelTransfer, ProgressHandle, ScopedTransfer, SizedTransfer,
+    Transfer,
+};
+
+/// The progress of a transfer, whatever its type.
+///
+/// This is implemented by [`Transfer`], [`SizedTransfer`], [`ScopedTransfer`],
+/// [`ParallelTransfer`], [`DuplexTransfer`], [`DirTransfer`] and [`ProgressHandle`], so code
+/// which displays progress can accept any of them, for instance as a `&dyn Progress`.
+/// # Example
+/// ```
+/// use transfer_progress::{Progress, SizedTransfer, Transfer};
+/// use std::io;
+/// fn percent(progress: &dyn Progress) -> String {
+///     match progress.fraction_transferred() {
+///         Some(fraction) => format!("{:.0}%", fraction * 100.0),
+///         None => format!("{} bytes", progress.transferred()),
+///     }
+/// }
+/// let transfer = Transfer::new(io::Cursor::new(vec![0; 1024]), io::sink());
+/// let sized = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+/// transfer.wait();
+/// sized.wait();
+/// assert_eq!(percent(&transfer), "1024 bytes");
+/// assert_eq!(percent(&sized), "100%");
+/// ```
+pub trait Progress {
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    fn transferred(&self) -> u64;
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    fn speed(&self) -> u64;
+
+    /// Returns the elapsed time since the transfer started, or zero if timing isn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    fn running_time(&self) -> Duration;
+
+    /// Tests if the transfer is complete.
+    fn is_complete(&self) -> bool;
+
+    /// Returns the size of the transfer, if it's known. By default, returns `None`.
+    fn size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the fraction of the transfer that is complete, between 0.0 and 1.0, or `None` if
+    /// the size of the transfer isn't known.
+    fn fraction_transferred(&self) -> Option<f64> {
+        let size = self.size()?;
+        if size == 0 {
+            return Some(1.0);
+        }
+        Some(self.transferred().min(size) as f64 / size as f64)
+    }
+
+    /// Returns the approximate remaining time until the transfer completes, based on its average
+    /// speed. Returns `None` if its size isn't known, or its speed is zero.
+    fn eta(&self) -> Option<Duration> {
+        let size = self.size()?;
+        let speed = self.speed();
+        if speed == 0 {
+            return None;
+        }
+        let remaining = size.saturating_sub(self.transferred());
+        Some(Duration::from_secs_f64(remaining as f64 / speed as f64))
+    }
+}
+
+impl<R, W> Progress for Transfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.state.size()
+    }
+}
+
+impl<R, W> Progress for SizedTransfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    fn transferred(&self) -> u64 {
+        (**self).transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        (**self).speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        (**self).running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        (**self).is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size())
+    }
+
+    fn fraction_transferred(&self) -> Option<f64> {
+        Some(self.fraction_transferred())
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        self.eta()
+    }
+}
+
+impl<'scope, R, W> Progress for ScopedTransfer<'scope, R, W>
+where
+    R: Read + Send + 'scope,
+    W: Write + Send + 'scope,
+{
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.progress_handle().size()
+    }
+}
+
+impl Progress for ParallelTransfer {
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size())
+    }
+}
+
+impl Progress for DuplexTransfer {
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+}
+
+impl Progress for DirTransfer {
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size())
+    }
+
+    fn fraction_transferred(&self) -> Option<f64> {
+        Some(self.fraction_transferred())
+    }
+}
+
+impl Progress for ProgressHandle {
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.size()
+    }
+
+    fn fraction_transferred(&self) -> Option<f64> {
+        self.fraction_transferred()
+    }
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+impl<R, W> Progress for crate::UringTransfer<R, W> {
+    fn transferred(&self) -> u64 {
+        self.transferred()
+    }
+
+    fn speed(&self) -> u64 {
+        self.speed()
+    }
+
+    fn running_time(&self) -> Duration {
+        self.running_time()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.is_complete()
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::prelude::*,
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{state::TransferState, SizedTransfer, Transfer};
+
+/// How often progress bars attached to transfers are updated.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The template used for the bars created by [`SizedTransfer::with_progress_bar`].
+const TEMPLATE: &str =
+    "{msg} [{elapsed_precise}] {wide_bar} {bytes}/{total_bytes} ({binary_bytes_per_sec}, {eta})";
+
+impl<R, W> Transfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Keeps `bar` up to date with the progress of the transfer, from a separate thread, until
+    /// the transfer completes.
+    ///
+    /// The bar's position is set to the number of bytes transferred, and its length to the size
+    /// of the transfer, if known. Once the transfer completes, the bar is finished, or abandoned
+    /// if the transfer failed or was cancelled.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::Transfer;
+    /// use indicatif::ProgressBar;
+    /// use std::fs::File;
+    /// let reader = File::open("file1.txt")?;
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = Transfer::new(reader, writer);
+    /// let bar = ProgressBar::new_spinner();
+    /// transfer.attach_progress_bar(&bar);
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn attach_progress_bar(&self, bar: &ProgressBar) {
+        let state = Arc::clone(&self.state);
+        let bar = bar.clone();
+        thread::spawn(move || update_progress_bar(&state, &bar));
+    }
+}
+
+impl<R, W> SizedTransfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `SizedTransfer`, along with a styled progress bar which is kept
+    /// up to date with it, as by [`attach_progress_bar`][Transfer::attach_progress_bar].
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::Read;
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let (transfer, bar) = SizedTransfer::with_progress_bar(reader, writer, 1024);
+    /// bar.set_message("file1.txt");
+    /// transfer.finish()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn with_progress_bar(reader: R, writer: W, size: u64) -> (Self, ProgressBar) {
+        let bar = ProgressBar::new(size).with_style(
+            ProgressStyle::with_template(TEMPLATE)
+                .expect("progress bar template is valid")
+                .progress_chars("=> "),
+        );
+        let transfer = Self::new(reader, writer, size);
+        transfer.attach_progress_bar(&bar);
+        (transfer, bar)
+    }
+}
+
+/// Updates `bar` with the progress of the transfer every [`UPDATE_INTERVAL`] until it completes.
+fn update_progress_bar(state: &TransferState, bar: &ProgressBar) {
+    loop {
+        let complete = state.wait_complete_timeout(UPDATE_INTERVAL);
+        if let Some(size) = state.size() {
+            bar.set_length(size);
+        }
+        bar.set_position(state.transferred());
+        if complete {
+            if state.failed.load(Ordering::Acquire) {
+                bar.abandon();
+            } else {
+                bar.finish();
+            }
+            return;
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::VecDeque,
+    io::{self, prelude::*},
+    mem,
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+};
+
+use crate::{SizedTransfer, TransferManager};
+
+/// The result of a job run by a [`TransferQueue`]: its index, in the order the jobs were
+/// [pushed][TransferQueue::push], and how it ended.
+pub type QueueResult<R, W> = (usize, io::Result<(R, W)>);
+
+/// Runs any number of transfers, at most a fixed number of them at a time.
+///
+/// Jobs are [pushed][TransferQueue::push] onto the queue, and started as soon as fewer than the
+/// maximum number of transfers are running, highest [priority][TransferQueue::push_with_priority]
+/// first, then in the order they were pushed. The progress of every job, whether it's waiting,
+/// running or finished, is counted together, and the results of the jobs are delivered as they
+/// finish.
+///
+/// Dropping a `TransferQueue` stops it accepting jobs, but the jobs already pushed carry on in the
+/// background until they're all finished.
+/// # Example
+/// ```no_run
+/// use transfer_progress::TransferQueue;
+/// use std::fs::File;
+/// use std::net::TcpStream;
+/// let queue = TransferQueue::with_concurrency(4);
+/// for name in ["a.bin", "b.bin", "c.bin", "d.bin", "e.bin"] {
+///     let file = File::open(name)?;
+///     let size = file.metadata()?.len();
+///     queue.push(file, TcpStream::connect("example.com:9000")?, size);
+/// }
+/// while !queue.is_complete() {
+///     println!("{:.1}%", queue.fraction_transferred() * 100.0);
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// for (index, result) in queue.finish() {
+///     if let Err(e) = result {
+///         eprintln!("Upload {} failed: {}", index, e);
+///     }
+/// }
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct TransferQueue<R, W> {
+    shared: Arc<Shared<R, W>>,
+    results: Mutex<mpsc::Receiver<QueueResult<R, W>>>,
+}
+
+/// The state shared between a [`TransferQueue`] and its workers.
+struct Shared<R, W> {
+    jobs: Mutex<Jobs<R, W>>,
+    /// Notified when a job is pushed, or the queue is closed.
+    job_pushed: Condvar,
+    /// Keeps track of every job which has been started.
+    manager: TransferManager,
+}
+
+struct Jobs<R, W> {
+    /// The jobs waiting to start, with their indices, in the order they'll start.
+    pending: VecDeque<(usize, Job<R, W>)>,
+    /// The number of jobs which have been pushed.
+    pushed: usize,
+    /// The combined size of every job which has been pushed.
+    size: u64,
+    /// Set once no more jobs will be pushed, so the workers can stop once the queue is empty.
+    closed: bool,
+}
+
+struct Job<R, W> {
+    reader: R,
+    writer: W,
+    size: u64,
+    priority: i32,
+}
+
+impl<R, W> Jobs<R, W> {
+    /// Adds `job` to the pending jobs, after any others of the same or higher priority.
+    fn insert(&mut self, index: usize, job: Job<R, W>) {
+        let position = self
+            .pending
+            .iter()
+            .position(|(_, pending)| pending.priority < job.priority)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(position, (index, job));
+    }
+}
+
+impl<R, W> TransferQueue<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates a new, empty `TransferQueue` which runs at most `concurrency` transfers at a time.
+    /// # Panics
+    /// Panics if `concurrency` is 0.
+    pub fn with_concurrency(concurrency: usize) -> Self {
+        assert!(
+            concurrency > 0,
+            "a transfer queue must run at least one transfer at a time"
+        );
+        let shared = Arc::new(Shared {
+            jobs: Mutex::new(Jobs {
+                pending: VecDeque::new(),
+                pushed: 0,
+                size: 0,
+                closed: false,
+            }),
+            job_pushed: Condvar::new(),
+            manager: TransferManager::new(),
+        });
+        let (sender, results) = mpsc::channel();
+        for _ in 0..concurrency {
+            let shared = Arc::clone(&shared);
+            let sender = sender.clone();
+            thread::spawn(move || work(&shared, &sender));
+        }
+        Self {
+            shared,
+            results: Mutex::new(results),
+        }
+    }
+
+    /// Adds a job copying `size` bytes from `reader` to `writer` to the queue, as for
+    /// [`SizedTransfer::new`], returning its index, counting from 0 in the order jobs are pushed.
+    ///
+    /// The job has a priority of 0, so it starts after any jobs already waiting with the same or
+    /// a higher priority.
+    pub fn push(&self, reader: R, writer: W, size: u64) -> usize {
+        self.push_with_priority(reader, writer, size, 0)
+    }
+
+    /// Like [`push`][Self::push], but gives the job a `priority`, so that it starts ahead of any
+    /// waiting jobs with a lower priority.
+    ///
+    /// Jobs which are already running aren't interrupted, so a job with a high priority still has
+    /// to wait for one of them to finish if the queue is full.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{Chunks, TransferQueue};
+    /// use std::{io, sync::mpsc};
+    /// let empty = || Chunks::new(mpsc::channel().1);
+    /// let queue = TransferQueue::with_concurrency(1);
+    /// // Keep the only worker busy until the other jobs are queued
+    /// let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    /// queue.push(Chunks::new(receiver), io::sink(), 0);
+    /// while queue.pending() > 0 {
+    ///     std::thread::yield_now();
+    /// }
+    /// let background = queue.push(empty(), io::sink(), 0);
+    /// let interactive = queue.push_with_priority(empty(), io::sink(), 0, 10);
+    /// drop(sender);
+    /// let order: Vec<usize> = queue.finish().map(|(index, _)| index).collect();
+    /// assert_eq!(order, [0, interactive, background]);
+    /// ```
+    pub fn push_with_priority(&self, reader: R, writer: W, size: u64, priority: i32) -> usize {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        let index = jobs.pushed;
+        jobs.pushed += 1;
+        jobs.size += size;
+        let job = Job {
+            reader,
+            writer,
+            size,
+            priority,
+        };
+        jobs.insert(index, job);
+        self.shared.job_pushed.notify_one();
+        index
+    }
+
+    /// Changes the priority of the waiting job at `index`, moving it after any others with the
+    /// same or a higher priority. Returns `false` if the job isn't waiting, because it has
+    /// already started, or there's no such job.
+    /// # Example
+    /// ```
+    /// use transfer_progress::{Chunks, TransferQueue};
+    /// use std::{io, sync::mpsc};
+    /// let empty = || Chunks::new(mpsc::channel().1);
+    /// let queue = TransferQueue::with_concurrency(1);
+    /// let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    /// queue.push(Chunks::new(receiver), io::sink(), 0);
+    /// while queue.pending() > 0 {
+    ///     std::thread::yield_now();
+    /// }
+    /// let first = queue.push(empty(), io::sink(), 0);
+    /// let second = queue.push(empty(), io::sink(), 0);
+    /// // The user bumps the second download to the top
+    /// assert!(queue.set_priority(second, 1));
+    /// assert!(!queue.set_priority(0, 1)); // Already running
+    /// drop(sender);
+    /// let order: Vec<usize> = queue.finish().map(|(index, _)| index).collect();
+    /// assert_eq!(order, [0, second, first]);
+    /// ```
+    pub fn set_priority(&self, index: usize, priority: i32) -> bool {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        let position = match jobs.pending.iter().position(|&(i, _)| i == index) {
+            Some(position) => position,
+            None => return false,
+        };
+        let (_, mut job) = jobs.pending.remove(position).unwrap();
+        job.priority = priority;
+        jobs.insert(index, job);
+        true
+    }
+
+    /// Returns the result of a job which has finished, if there is one that hasn't been returned
+    /// yet, without blocking.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferQueue;
+    /// use std::io;
+    /// let queue = TransferQueue::with_concurrency(2);
+    /// queue.push(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+    /// let (index, result) = loop {
+    ///     if let Some(finished) = queue.try_next_result() {
+    ///         break finished;
+    ///     }
+    /// };
+    /// assert_eq!(index, 0);
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn try_next_result(&self) -> Option<QueueResult<R, W>> {
+        self.results.lock().unwrap().try_recv().ok()
+    }
+
+    /// Consumes the `TransferQueue`, returning an iterator over the results of the jobs, in the
+    /// order they finish, which blocks until each one is available.
+    ///
+    /// No more jobs can be pushed, and the iterator ends once every job has finished. Results
+    /// already returned by [`try_next_result`][Self::try_next_result] aren't returned again.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TransferQueue;
+    /// use std::io;
+    /// let queue = TransferQueue::with_concurrency(2);
+    /// for size in [1024, 2048, 4096] {
+    ///     queue.push(io::Cursor::new(vec![0; size]), Vec::new(), size as u64);
+    /// }
+    /// let mut results: Vec<_> = queue.finish().collect();
+    /// results.sort_by_key(|&(index, _)| index);
+    /// let (_, writer) = results.pop().unwrap().1?;
+    /// assert_eq!(writer.len(), 4096);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn finish(mut self) -> impl Iterator<Item = QueueResult<R, W>> {
+        self.shared.close();
+        // The workers hold the senders, so the results end once they've all stopped
+        let (_, empty) = mpsc::channel();
+        mem::replace(self.results.get_mut().unwrap(), empty).into_iter()
+    }
+
+    /// Tests if every job which has been pushed is finished.
+    pub fn is_complete(&self) -> bool {
+        let jobs = self.shared.jobs.lock().unwrap();
+        jobs.pending.is_empty() && self.shared.manager.running() == 0
+    }
+
+    /// Returns the number of jobs waiting to start.
+    pub fn pending(&self) -> usize {
+        self.shared.jobs.lock().unwrap().pending.len()
+    }
+
+    /// Returns the number of jobs which are running.
+    pub fn running(&self) -> usize {
+        self.shared.manager.running()
+    }
+
+    /// Returns the number of jobs which have finished, successfully or not.
+    pub fn finished(&self) -> usize {
+        // A job may start between the two counts, so don't let them underflow
+        let started = self.shared.manager.len();
+        started.saturating_sub(self.shared.manager.running())
+    }
+
+    /// Returns the total number of bytes transferred by all the jobs.
+    pub fn transferred(&self) -> u64 {
+        self.shared.manager.transferred()
+    }
+
+    /// Returns the combined size of all the jobs.
+    pub fn size(&self) -> u64 {
+        self.shared.jobs.lock().unwrap().size
+    }
+
+    /// Returns the fraction of the combined size of all the jobs that has been transferred,
+    /// between 0.0 and 1.0.
+    pub fn fraction_transferred(&self) -> f64 {
+        let size = self.size();
+        if size == 0 {
+            return 1.0;
+        }
+        self.transferred().min(size) as f64 / size as f64
+    }
+
+    /// Returns the combined average speed, in bytes per second, of the jobs which are running.
+    pub fn speed(&self) -> u64 {
+        self.shared.manager.speed()
+    }
+}
+
+impl<R, W> Drop for TransferQueue<R, W> {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+impl<R, W> Shared<R, W> {
+    /// Stops any more jobs being pushed, letting the workers stop once the queue is empty.
+    fn close(&self) {
+        self.jobs.lock().unwrap().closed = true;
+        self.job_pushed.notify_all();
+    }
+}
+
+/// Runs jobs from the queue, one at a time, sending their results to `results`, until the queue
+/// is closed and empty.
+fn work<R, W>(shared: &Shared<R, W>, results: &mpsc::Sender<QueueResult<R, W>>)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    loop {
+        let mut jobs = shared.jobs.lock().unwrap();
+        let (index, job) = loop {
+            if let Some(next) = jobs.pending.pop_front() {
+                break next;
+            }
+            if jobs.closed {
+                return;
+            }
+            jobs = shared.job_pushed.wait(jobs).unwrap();
+        };
+        let transfer = SizedTransfer::new(job.reader, job.writer, job.size);
+        // Register the transfer before releasing the lock, so its size is never left uncounted
+        shared.manager.add(transfer.progress_handle());
+        drop(jobs);
+        // Nobody may be listening for the results any more, which is fine
+        let _ = results.send((index, transfer.finish()));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    any::Any,
+    fs::File,
+    io::{BufReader, Take},
+};
+
+/// How far ahead of the reader the OS is asked to read.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+const READAHEAD_WINDOW: u64 = 8 * 1024 * 1024;
+
+/// Hints to the OS that a file is about to be read, so it can read ahead of the reader.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub(crate) struct Readahead {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fd: std::os::unix::io::RawFd,
+    /// The offset in the file the reader has reached.
+    position: u64,
+    /// The offset in the file up to which the OS has been asked to read ahead.
+    advised: u64,
+}
+
+impl Readahead {
+    /// Returns a `Readahead` for `reader` if it's a [`File`], or a file wrapped in a [`Take`] or
+    /// [`BufReader`], and reading ahead is supported on this platform.
+    pub(crate) fn for_reader(reader: &dyn Any) -> Option<Self> {
+        let file = reader
+            .downcast_ref::<File>()
+            .or_else(|| reader.downcast_ref::<Take<File>>().map(Take::get_ref))
+            .or_else(|| {
+                reader
+                    .downcast_ref::<BufReader<File>>()
+                    .map(BufReader::get_ref)
+            })?;
+        Self::for_file(file)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn for_file(file: &File) -> Option<Self> {
+        use std::os::unix::io::AsRawFd;
+        let fd = file.as_raw_fd();
+        // SAFETY: `fd` is a valid file descriptor, since it belongs to `file`.
+        let position = unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) };
+        if position < 0 {
+            // Not seekable, so there's nothing to read ahead
+            return None;
+        }
+        // SAFETY: As above. This is only a hint, so failure is harmless.
+        unsafe { libc::posix_fadvise(fd, 0, 0, libc::POSIX_FADV_SEQUENTIAL) };
+        let mut readahead = Self {
+            fd,
+            position: position as u64,
+            advised: position as u64,
+        };
+        readahead.advance(0);
+        Some(readahead)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn for_file(_file: &File) -> Option<Self> {
+        None
+    }
+
+    /// Records that `bytes` more bytes have been read, and asks the OS to read further ahead if
+    /// the reader is getting close to the end of the region it was last asked to read.
+    pub(crate) fn advance(&mut self, bytes: u64) {
+        self.position += bytes;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.advised.saturating_sub(self.position) < READAHEAD_WINDOW / 2 {
+            let start = self.advised.max(self.position);
+            let end = self.position + READAHEAD_WINDOW;
+            // SAFETY: `fd` belongs to the reader, which outlives us. This is only a hint, so
+            // failure is harmless.
+            unsafe {
+                libc::posix_fadvise(
+                    self.fd,
+                    start as libc::off_t,
+                    (end - start) as libc::off_t,
+                    libc::POSIX_FADV_WILLNEED,
+                )
+            };
+            self.advised = end;
+        }
+    }
+}
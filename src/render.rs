@@ -0,0 +1,84 @@
+use std::{
+    io::{self, prelude::*},
+    time::Duration,
+};
+
+use crate::{format, SizedTransfer};
+
+impl<R, W> SizedTransfer<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Returns a one-line progress bar for the transfer, at most `width` characters wide, like
+    /// `[#####-----] 45% 12.3 MiB/s ETA 00:12`.
+    ///
+    /// The bar takes up whatever room is left by the percentage, speed and remaining time, and is
+    /// left out if there isn't enough room for it. Without the `bytesize` or `humansize` feature,
+    /// the speed is given in bytes per second.
+    /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+    /// transfer.wait();
+    /// let line = transfer.render_line(60);
+    /// assert!(line.starts_with("[##########"));
+    /// assert!(line.contains("] 100% "));
+    /// assert!(line.chars().count() <= 60);
+    /// ```
+    pub fn render_line(&self, width: usize) -> String {
+        let speed = format::speed(self.speed(), false, self.config().speed_in_bits);
+        render(self.fraction_transferred(), &speed, self.eta(), width)
+    }
+
+    /// Writes the [progress bar][Self::render_line] to `writer`, preceded by a carriage return so
+    /// that it replaces the last one written on a terminal, then flushes it.
+    /// # Example
+    /// ```no_run
+    /// use transfer_progress::SizedTransfer;
+    /// use std::fs::File;
+    /// use std::io::{self, Read};
+    /// let reader = File::open("file1.txt")?.take(1024); // Bytes
+    /// let writer = File::create("file2.txt")?;
+    /// let transfer = SizedTransfer::new(reader, writer, 1024);
+    /// while !transfer.is_complete() {
+    ///     transfer.render_to(&mut io::stderr(), 80)?;
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    /// }
+    /// transfer.render_to(&mut io::stderr(), 80)?;
+    /// eprintln!();
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn render_to(&self, writer: &mut impl Write, width: usize) -> io::Result<()> {
+        write!(writer, "\r{}", self.render_line(width))?;
+        writer.flush()
+    }
+}
+
+/// The narrowest the bar is drawn, including its brackets. Any narrower, and it's left out.
+const MIN_BAR_WIDTH: usize = 5;
+
+/// Renders a progress bar at most `width` characters wide for a transfer which is `fraction`
+/// complete, and going at `speed` per second.
+fn render(fraction: f64, speed: &str, eta: Option<Duration>, width: usize) -> String {
+    let status = format!(
+        "{:.0}% {}/s ETA {}",
+        fraction * 100.0,
+        speed,
+        eta.map_or_else(|| "--:--".to_string(), format::clock)
+    );
+    let status_width = status.chars().count();
+    // The bar is separated from the status by a space
+    if width < status_width + 1 + MIN_BAR_WIDTH {
+        return status.chars().take(width).collect();
+    }
+    let inner = width - status_width - 3;
+    let filled = ((fraction.clamp(0.0, 1.0) * inner as f64).round() as usize).min(inner);
+    format!(
+        "[{}{}] {}",
+        "#".repeat(filled),
+        "-".repeat(inner - filled),
+        status
+    )
+}
@@ -0,0 +1,111 @@
+use std::{
+    io::{self, prelude::*},
+    time::Duration,
+};
+
+/// The number of times a transfer retries by default, as used by [`RetryPolicy::default`].
+const DEFAULT_MAX_RETRIES: u32 = 5;
+/// How long a transfer waits before retrying the first time, by default.
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// The longest a transfer waits between retries, by default.
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How a transfer created with [`Transfer::with_retries`][crate::Transfer::with_retries] retries
+/// after its reader fails.
+///
+/// The wait before each retry starts at the initial backoff, and doubles with each retry, up to
+/// the maximum backoff. The count of retries is reset whenever data is read successfully, so it
+/// only limits how many times in a row the transfer can fail to make progress.
+/// # Example
+/// ```
+/// use transfer_progress::RetryPolicy;
+/// use std::time::Duration;
+/// let policy = RetryPolicy::new(10)
+///     .initial_backoff(Duration::from_millis(500))
+///     .max_backoff(Duration::from_secs(10));
+/// assert_eq!(policy.max_retries(), 10);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a new `RetryPolicy`, which retries up to `max_retries` times in a row, with the
+    /// default backoff.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Sets how long to wait before retrying the first time. By default, this is 100
+    /// milliseconds.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the longest to wait between retries. By default, this is 30 seconds.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Returns the number of times in a row the transfer is retried before it fails.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns how long to wait before retry number `retry`, counting from 0.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(retry))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Returns a `RetryPolicy` which retries up to 5 times in a row.
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RETRIES)
+    }
+}
+
+/// Counts the bytes read from `inner`, and records whether reading from it failed.
+pub(crate) struct ReadTracker<'a, R> {
+    pub(crate) inner: &'a mut R,
+    pub(crate) read: u64,
+    pub(crate) failed: bool,
+}
+
+impl<'a, R> ReadTracker<'a, R> {
+    pub(crate) fn new(inner: &'a mut R) -> Self {
+        Self {
+            inner,
+            read: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ReadTracker<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(read) => {
+                self.read += read as u64;
+                Ok(read)
+            }
+            Err(e) => {
+                if e.kind() != io::ErrorKind::Interrupted {
+                    self.failed = true;
+                }
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A window of time each day, in UTC, during which a transfer is allowed to run.
+///
+/// Outside the window, a transfer with a [schedule][crate::TransferBuilder::schedule] pauses
+/// until the window next opens. A window whose end is earlier than its start wraps around
+/// midnight, so, for instance, a window from 22:00 to 06:00 covers the night.
+/// # Example
+/// ```
+/// use transfer_progress::TimeWindow;
+/// use std::time::Duration;
+/// // Only run between 1am and 5am
+/// let window = TimeWindow::hours(1, 5);
+/// assert!(window.contains(Duration::from_secs(2 * 60 * 60)));
+/// assert!(!window.contains(Duration::from_secs(12 * 60 * 60)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// The time since midnight at which the window opens.
+    start: Duration,
+    /// The time since midnight at which the window closes.
+    end: Duration,
+}
+
+impl TimeWindow {
+    /// Creates a new `TimeWindow`, opening at `start` and closing at `end`, both measured from
+    /// midnight UTC.
+    ///
+    /// # Panics
+    /// Panics if either `start` or `end` is a day or longer, or if they are equal.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TimeWindow;
+    /// use std::time::Duration;
+    /// // From 22:30 to 06:00
+    /// let window = TimeWindow::new(
+    ///     Duration::from_secs(22 * 60 * 60 + 30 * 60),
+    ///     Duration::from_secs(6 * 60 * 60),
+    /// );
+    /// ```
+    pub fn new(start: Duration, end: Duration) -> Self {
+        assert!(
+            start < DAY && end < DAY,
+            "time window must start and end within a day"
+        );
+        assert!(start != end, "time window must not be empty");
+        Self { start, end }
+    }
+
+    /// Creates a new `TimeWindow`, opening at the hour `start` and closing at the hour `end`, both
+    /// in UTC.
+    ///
+    /// # Panics
+    /// Panics if either `start` or `end` is greater than 23, or if they are equal.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TimeWindow;
+    /// let window = TimeWindow::hours(1, 5); // 01:00 to 05:00
+    /// ```
+    pub fn hours(start: u32, end: u32) -> Self {
+        let hour = |h: u32| Duration::from_secs(u64::from(h) * 60 * 60);
+        Self::new(hour(start), hour(end))
+    }
+
+    /// Tests if the window is open at `time_of_day`, measured from midnight UTC.
+    /// # Example
+    /// ```
+    /// use transfer_progress::TimeWindow;
+    /// use std::time::Duration;
+    /// let window = TimeWindow::hours(22, 6);
+    /// assert!(window.contains(Duration::from_secs(23 * 60 * 60)));
+    /// assert!(window.contains(Duration::from_secs(3 * 60 * 60)));
+    /// assert!(!window.contains(Duration::from_secs(12 * 60 * 60)));
+    /// ```
+    pub fn contains(&self, time_of_day: Duration) -> bool {
+        if self.start < self.end {
+            self.start <= time_of_day && time_of_day < self.end
+        } else {
+            time_of_day >= self.start || time_of_day < self.end
+        }
+    }
+
+    /// Returns the time from `time_of_day` until the window next opens, or zero if it's open.
+    pub(crate) fn until_open(&self, time_of_day: Duration) -> Duration {
+        if self.contains(time_of_day) {
+            Duration::ZERO
+        } else {
+            until(time_of_day, self.start)
+        }
+    }
+
+    /// Returns the time from `time_of_day` until the window next closes.
+    fn until_close(&self, time_of_day: Duration) -> Duration {
+        until(time_of_day, self.end)
+    }
+
+    /// Returns how long the window is open each day.
+    fn length(&self) -> Duration {
+        until(self.start, self.end)
+    }
+
+    /// Returns how much wall-clock time, starting at `time_of_day`, it takes for the window to be
+    /// open for `active` in total.
+    pub(crate) fn wall_time(&self, mut active: Duration, mut time_of_day: Duration) -> Duration {
+        let mut total = Duration::ZERO;
+        // Whole days only need counting once the window is aligned to its start
+        let wait = self.until_open(time_of_day);
+        if !wait.is_zero() {
+            total += wait;
+            time_of_day = self.start;
+        }
+        loop {
+            let open = self.until_close(time_of_day);
+            if active <= open {
+                return total + active;
+            }
+            active -= open;
+            total += open + until(self.end, self.start);
+            time_of_day = self.start;
+            let days = (active.as_secs_f64() / self.length().as_secs_f64()).floor();
+            total += DAY.mul_f64(days);
+            active = active.saturating_sub(self.length().mul_f64(days));
+        }
+    }
+}
+
+/// Returns the time from `from` until the clock next reads `to`, both measured from midnight.
+fn until(from: Duration, to: Duration) -> Duration {
+    if to >= from {
+        to - from
+    } else {
+        DAY - from + to
+    }
+}
+
+/// Returns the current time since midnight UTC.
+pub(crate) fn time_of_day() -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Duration::new(now.as_secs() % DAY.as_secs(), now.subsec_nanos())
+}
@@ -0,0 +1,126 @@
+use std::{
+    io::{self, prelude::*},
+    sync::Arc,
+    thread::{Scope, ScopedJoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    cancel::CancelOnDrop,
+    check_cancelled, copy,
+    state::{CompleteOnPanic, TransferState},
+    ProgressHandle, TransferBuilder, TransferError,
+};
+
+/// Monitors the progress of a transfer from a [reader][Read] to a [writer][Write] which needn't
+/// be `'static`, running on a thread in a [scope][std::thread::scope].
+///
+/// This allows borrowed readers and writers, such as `&mut File`, to be used. The transfer must
+/// finish before the scope ends; if it's not [finished][ScopedTransfer::finish] explicitly, the
+/// scope waits for it. [Reading ahead][TransferBuilder::readahead] isn't supported for scoped
+/// transfers.
+/// # Example
+/// ```
+/// use transfer_progress::ScopedTransfer;
+/// use std::io;
+/// let mut reader = io::Cursor::new(vec![0; 4096]);
+/// let mut writer = Vec::new();
+/// std::thread::scope(|scope| {
+///     let transfer = ScopedTransfer::new(scope, &mut reader, &mut writer);
+///     while !transfer.is_complete() {
+///         std::thread::yield_now();
+///     }
+///     transfer.finish()
+/// })?;
+/// assert_eq!(writer.len(), 4096);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct ScopedTransfer<'scope, R, W> {
+    state: Arc<TransferState>,
+    handle: ScopedJoinHandle<'scope, Result<(R, W), TransferError>>,
+    _cancel_on_drop: CancelOnDrop,
+}
+
+impl<'scope, R, W> ScopedTransfer<'scope, R, W>
+where
+    R: Read + Send + 'scope,
+    W: Write + Send + 'scope,
+{
+    /// Creates and starts a new `ScopedTransfer` on a thread in `scope`.
+    pub fn new<'env>(scope: &'scope Scope<'scope, 'env>, reader: R, writer: W) -> Self {
+        TransferBuilder::new().build_scoped(scope, reader, writer)
+    }
+
+    pub(crate) fn with_builder<'env>(
+        builder: TransferBuilder,
+        scope: &'scope Scope<'scope, 'env>,
+        reader: R,
+        writer: W,
+    ) -> Self {
+        let limit = builder.copy_limit();
+        let parts = builder.into_parts();
+        let state = Arc::clone(&parts.state);
+        let options = parts.options;
+        let handle = scope.spawn(move || {
+            let _complete = CompleteOnPanic(&state);
+            match limit {
+                Some((limit, exact)) => {
+                    copy::copy_limited(&state, options, reader, writer, limit, exact)
+                }
+                None => copy::copy(&state, options, reader, writer),
+            }
+        });
+        Self {
+            _cancel_on_drop: CancelOnDrop::new(&parts.state, parts.config.cancel_on_drop),
+            state: parts.state,
+            handle,
+        }
+    }
+
+    /// Consumes the `ScopedTransfer`, blocking until the transfer is complete.
+    ///
+    /// If the transfer was successful, returns `Ok(reader, writer)`, otherwise returns
+    /// the error. If the thread performing the transfer panicked, returns
+    /// [`TransferError::Panicked`].
+    pub fn finish(self) -> io::Result<(R, W)> {
+        let result = self
+            .handle
+            .join()
+            .unwrap_or_else(|payload| Err(TransferError::from_panic(payload)));
+        check_cancelled(&self.state, result).map_err(io::Error::from)
+    }
+
+    /// Asks the transfer to stop as soon as possible, after which [`finish`][Self::finish]
+    /// returns [`TransferError::Cancelled`].
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the elapsed time since the transfer started, or zero if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// [tracked][TransferBuilder::track_timing].
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring this transfer, which doesn't depend on the
+    /// types of its reader and writer, and can be used outside the scope.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+}
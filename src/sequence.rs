@@ -0,0 +1,87 @@
+use std::{fmt, io, sync::Arc};
+
+use crate::state::TransferState;
+
+/// A reader which reads each of a sequence of named readers in turn, calling a callback as each
+/// one is finished, as used by [`Transfer::sequence`][crate::Transfer::sequence].
+pub struct Sequence<R, N> {
+    items: Vec<(R, N)>,
+    /// The index of the item currently being read.
+    current: usize,
+    on_item_complete: Box<dyn FnMut(&N) + Send>,
+    /// The state of the transfer reading the sequence, which is told when each item starts.
+    state: Option<Arc<TransferState>>,
+}
+
+impl<R, N> Sequence<R, N> {
+    pub(crate) fn new<F>(items: Vec<(R, N)>, on_item_complete: F) -> Self
+    where
+        F: FnMut(&N) + Send + 'static,
+    {
+        Self {
+            items,
+            current: 0,
+            on_item_complete: Box::new(on_item_complete),
+            state: None,
+        }
+    }
+
+    /// Keeps `state` informed of which item is being read.
+    pub(crate) fn track(&mut self, state: Arc<TransferState>) {
+        state.start_segment(self.current);
+        self.state = Some(state);
+    }
+
+    /// Returns the number of items which have been read to the end.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let items = vec![(io::Cursor::new(b"abc".to_vec()), "a")];
+    /// let transfer = Transfer::sequence(items, io::sink(), |_| {});
+    /// let (sequence, _) = transfer.finish()?;
+    /// assert_eq!(sequence.completed(), 1);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn completed(&self) -> usize {
+        self.current
+    }
+
+    /// Consumes the `Sequence`, returning the readers and their names.
+    pub fn into_inner(self) -> Vec<(R, N)> {
+        self.items
+    }
+}
+
+impl<R: io::Read, N> io::Read for Sequence<R, N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while let Some((reader, name)) = self.items.get_mut(self.current) {
+            match reader.read(buf)? {
+                0 => {
+                    (self.on_item_complete)(name);
+                    self.current += 1;
+                    if let Some(state) = &self.state {
+                        state.start_segment(self.current);
+                    }
+                }
+                len => return Ok(len),
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl<R, N: fmt::Debug> fmt::Debug for Sequence<R, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sequence")
+            .field(
+                "names",
+                &self.items.iter().map(|(_, name)| name).collect::<Vec<_>>(),
+            )
+            .field("completed", &self.current)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,125 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::TransferId;
+
+/// The statistics of a transfer at a particular moment.
+///
+/// With the `serde` feature enabled, snapshots can be serialized and deserialized, for instance to
+/// report progress to another process.
+/// # Example
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use transfer_progress::{ProgressSnapshot, SizedTransfer};
+/// use std::io;
+/// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+/// let (_, stats) = transfer.finish_with_stats();
+/// let json = serde_json::to_string(&stats)?;
+/// let received: ProgressSnapshot = serde_json::from_str(&json)?;
+/// assert_eq!(received, stats);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "serde"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ProgressSnapshot {
+    /// The ID of the transfer.
+    pub id: TransferId,
+    /// The [name][crate::TransferBuilder::name] of the transfer, if it has one.
+    pub name: Option<Arc<str>>,
+    /// The number of bytes transferred.
+    pub transferred: u64,
+    /// The total size of the transfer, if known.
+    pub size: Option<u64>,
+    /// The time elapsed since the transfer started.
+    pub elapsed: Duration,
+    /// The average speed of the transfer, in bytes per second.
+    pub speed: u64,
+    /// Whether the transfer was complete.
+    pub complete: bool,
+}
+
+impl ProgressSnapshot {
+    /// Returns a fraction between 0.0 and 1.0 representing the state of the transfer, or `None`
+    /// if its size isn't known.
+    pub fn fraction_transferred(&self) -> Option<f64> {
+        let size = self.size?;
+        if size == 0 {
+            return Some(1.0);
+        }
+        Some(self.transferred.min(size) as f64 / size as f64)
+    }
+
+    /// Tests if more bytes had been transferred than the size of the transfer, because the reader
+    /// had more data than expected. Returns `false` if its size isn't known.
+    pub fn overshot(&self) -> bool {
+        self.size.is_some_and(|size| self.transferred > size)
+    }
+
+    /// Returns the approximate remaining time until the transfer completes, based on its average
+    /// speed. Returns `None` if its size isn't known, its speed was zero, or timing wasn't being
+    /// [tracked][crate::TransferBuilder::track_timing].
+    pub fn eta(&self) -> Option<Duration> {
+        let size = self.size?;
+        if self.speed == 0 {
+            return None;
+        }
+        let remaining = size.saturating_sub(self.transferred);
+        Some(Duration::from_secs_f64(
+            remaining as f64 / self.speed as f64,
+        ))
+    }
+
+    /// Returns the snapshot's statistics as labelled fields, for consumption by other programs
+    /// rather than humans.
+    ///
+    /// The schema is stable: the same fields are always returned, in this order, with values
+    /// formatted as described, or empty if unknown.
+    ///
+    /// | Field               | Value                                                     |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | `transferred_bytes` | Bytes transferred, as an integer                          |
+    /// | `size_bytes`        | Total size in bytes, as an integer                        |
+    /// | `speed_bps`         | Average speed in bytes per second, as an integer          |
+    /// | `eta_secs`          | Estimated seconds remaining, to 3 decimal places          |
+    /// | `percent`           | Percentage transferred, from 0 to 100, to 1 decimal place |
+    /// | `elapsed_secs`      | Seconds elapsed since the start, to 3 decimal places      |
+    /// # Example
+    /// ```
+    /// use transfer_progress::SizedTransfer;
+    /// use std::io;
+    /// let transfer = SizedTransfer::new(io::Cursor::new(vec![0; 1024]), io::sink(), 1024);
+    /// let (_, stats) = transfer.finish_with_stats();
+    /// let fields: Vec<_> = stats.fields().collect();
+    /// assert_eq!(fields[0], ("transferred_bytes", "1024".to_owned()));
+    /// assert_eq!(fields[1], ("size_bytes", "1024".to_owned()));
+    /// assert_eq!(fields[4], ("percent", "100.0".to_owned()));
+    /// ```
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, String)> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        vec![
+            ("transferred_bytes", self.transferred.to_string()),
+            (
+                "size_bytes",
+                optional(self.size.map(|size| size.to_string())),
+            ),
+            ("speed_bps", self.speed.to_string()),
+            (
+                "eta_secs",
+                optional(self.eta().map(|eta| format!("{:.3}", eta.as_secs_f64()))),
+            ),
+            (
+                "percent",
+                optional(
+                    self.fraction_transferred()
+                        .map(|fraction| format!("{:.1}", fraction * 100.0)),
+                ),
+            ),
+            ("elapsed_secs", format!("{:.3}", self.elapsed.as_secs_f64())),
+        ]
+        .into_iter()
+    }
+}
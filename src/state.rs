@@ -0,0 +1,809 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{ProgressSnapshot, TransferConfig, TransferError, TransferId};
+
+/// The default length of the window over which the windowed speed is measured.
+pub(crate) const SPEED_WINDOW: Duration = Duration::from_secs(5);
+/// The minimum time between two samples being recorded in the speed window.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stored in place of the size of a transfer whose size isn't known.
+const UNKNOWN_SIZE: u64 = u64::MAX;
+/// Stored in place of the time at which something which hasn't happened yet happened.
+const NOT_YET: u64 = u64::MAX;
+
+/// State shared between a transfer and the thread performing it.
+pub(crate) struct TransferState {
+    pub(crate) id: TransferId,
+    pub(crate) name: Option<Arc<str>>,
+    /// The total size of the transfer, or `UNKNOWN_SIZE`.
+    size: AtomicU64,
+    pub(crate) transferred: AtomicU64,
+    /// The number of bytes which had already been transferred before the transfer started, and
+    /// are included in `transferred`.
+    offset: AtomicU64,
+    /// Whether `transferred` counts bytes written, rather than bytes read.
+    count_written: bool,
+    /// The number of bytes read from the reader.
+    read: AtomicU64,
+    /// The number of bytes written to the writer.
+    written: AtomicU64,
+    /// When the state was created, which `last_progress` is measured from, or `None` if timing
+    /// isn't being tracked.
+    created: Option<Instant>,
+    /// Nanoseconds since `created` at which bytes were last transferred.
+    last_progress: AtomicU64,
+    /// Nanoseconds since `created` at which bytes were first read, or `NOT_YET` if none have been.
+    first_byte: AtomicU64,
+    /// The number of buffers that have been read and written.
+    pub(crate) chunks: AtomicU64,
+    /// The length of the largest chunk read from the reader.
+    pub(crate) max_chunk: AtomicU64,
+    pub(crate) complete: AtomicBool,
+    /// The number of flushes forced by the transfer's `max_in_flight` setting.
+    pub(crate) forced_flushes: AtomicU64,
+    /// Set if the transfer copied inside the kernel, rather than through a buffer.
+    pub(crate) used_fast_path: AtomicBool,
+    /// The number of chunks read by a pipelined transfer's reading thread which are waiting to be
+    /// written.
+    pub(crate) queued: AtomicU64,
+    /// The number of bytes in the chunks counted by `queued`.
+    queued_bytes: AtomicU64,
+    /// The most bytes `queued_bytes` has ever reached.
+    pub(crate) queue_high_water: AtomicU64,
+    /// Set while the transfer is paused.
+    paused: AtomicBool,
+    /// Set to ask the thread performing the transfer to stop.
+    pub(crate) cancel_requested: AtomicBool,
+    /// Called when cancellation is requested, to wake up a transfer which may be blocked for a
+    /// long time, such as one waiting to read from a socket. Dropped once the transfer completes.
+    cancel_hook: Mutex<Option<Box<dyn Fn() + Send + Sync>>>,
+    /// Set, before `complete`, if the transfer stopped because it was cancelled. The thread
+    /// performing the transfer still returns the reader and writer in this case.
+    pub(crate) cancelled: AtomicBool,
+    /// Set, before `complete`, if the transfer ended with an error.
+    pub(crate) failed: AtomicBool,
+    /// A description of the error the transfer ended with, if any.
+    error: Mutex<Option<String>>,
+    /// The digest of the data transferred, once the transfer has completed successfully.
+    digest: Mutex<Option<Vec<u8>>>,
+    /// Nanoseconds spent paused outside the transfer's schedule.
+    parked: AtomicU64,
+    /// The index of the item of a [`Sequence`][crate::Sequence] being read, and the number of
+    /// bytes transferred before it.
+    segment: Mutex<(usize, u64)>,
+    /// `None` if timing isn't being tracked for this transfer.
+    timing: Option<Timing>,
+    /// Locked while notifying or waiting on `progress_made`.
+    progress_lock: Mutex<()>,
+    /// Notified whenever bytes are transferred, and when the transfer completes.
+    progress_made: Condvar,
+}
+
+impl TransferState {
+    pub(crate) fn new(config: &TransferConfig, size: Option<u64>) -> Self {
+        Self {
+            id: TransferId::next(),
+            name: config.name.as_deref().map(Arc::from),
+            size: AtomicU64::new(size.unwrap_or(UNKNOWN_SIZE)),
+            transferred: AtomicU64::new(config.offset),
+            offset: AtomicU64::new(config.offset),
+            count_written: config.count_written,
+            read: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+            created: config.track_timing.then(Instant::now),
+            last_progress: AtomicU64::new(0),
+            first_byte: AtomicU64::new(NOT_YET),
+            chunks: AtomicU64::new(0),
+            max_chunk: AtomicU64::new(0),
+            complete: AtomicBool::new(false),
+            forced_flushes: AtomicU64::new(0),
+            used_fast_path: AtomicBool::new(false),
+            queued: AtomicU64::new(0),
+            queued_bytes: AtomicU64::new(0),
+            queue_high_water: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            cancel_requested: AtomicBool::new(false),
+            cancel_hook: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+            failed: AtomicBool::new(false),
+            error: Mutex::new(None),
+            digest: Mutex::new(None),
+            parked: AtomicU64::new(0),
+            segment: Mutex::new((0, 0)),
+            timing: if config.track_timing {
+                Some(Timing::new(config, config.offset))
+            } else {
+                None
+            },
+            progress_lock: Mutex::new(()),
+            progress_made: Condvar::new(),
+        }
+    }
+
+    /// Tests if the transfer is complete.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of bytes transferred thus far, including any offset.
+    pub(crate) fn transferred(&self) -> u64 {
+        self.transferred.load(Ordering::Acquire)
+    }
+
+    /// Returns the total size of the transfer, if known.
+    pub(crate) fn size(&self) -> Option<u64> {
+        match self.size.load(Ordering::Acquire) {
+            UNKNOWN_SIZE => None,
+            size => Some(size),
+        }
+    }
+
+    /// Changes the total size of the transfer.
+    pub(crate) fn set_size(&self, size: u64) {
+        debug_assert!(size != UNKNOWN_SIZE, "transfer size is too large");
+        self.size.store(size, Ordering::Release);
+    }
+
+    /// Returns the number of bytes transferred since the transfer started, not counting its
+    /// offset.
+    pub(crate) fn transferred_since_start(&self) -> u64 {
+        // Load the offset first, so it's never more than the number transferred
+        let offset = self.offset.load(Ordering::Acquire);
+        self.transferred
+            .load(Ordering::Acquire)
+            .saturating_sub(offset)
+    }
+
+    /// Records that `bytes` more bytes have been read from the reader.
+    pub(crate) fn add_read(&self, bytes: u64) {
+        if bytes > 0 && self.first_byte.load(Ordering::Relaxed) == NOT_YET {
+            if let Some(now) = self.since_created() {
+                // Only the first read is recorded, even if several threads read at once
+                let _ = self.first_byte.compare_exchange(
+                    NOT_YET,
+                    now,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+            }
+        }
+        self.read.fetch_add(bytes, Ordering::Release);
+        if !self.count_written {
+            self.add_transferred(bytes);
+        }
+    }
+
+    /// Records that `bytes` more bytes have been written to the writer.
+    pub(crate) fn add_written(&self, bytes: u64) {
+        self.written.fetch_add(bytes, Ordering::Release);
+        if self.count_written {
+            self.add_transferred(bytes);
+        }
+    }
+
+    /// Returns how long after the transfer was created the first bytes were read, or `None` if
+    /// none have been yet.
+    pub(crate) fn time_to_first_byte(&self) -> Option<Duration> {
+        match self.first_byte.load(Ordering::Acquire) {
+            NOT_YET => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    /// Returns how long it has been since bytes were last transferred, or since the transfer was
+    /// created if none have been.
+    pub(crate) fn idle_time(&self) -> Duration {
+        let last_progress = Duration::from_nanos(self.last_progress.load(Ordering::Acquire));
+        self.created.map_or(Duration::ZERO, |created| {
+            created.elapsed().saturating_sub(last_progress)
+        })
+    }
+
+    /// Returns the nanoseconds elapsed since the state was created, or `None` if timing isn't
+    /// being tracked.
+    fn since_created(&self) -> Option<u64> {
+        self.created
+            .map(|created| created.elapsed().as_nanos() as u64)
+    }
+
+    /// Tests if the transfer has stalled: it's running, not paused, and no bytes have been
+    /// transferred for at least `threshold`. Always `false` if timing isn't being tracked.
+    pub(crate) fn is_stalled(&self, threshold: Duration) -> bool {
+        self.is_timed() && !self.is_complete() && !self.is_paused() && self.idle_time() >= threshold
+    }
+
+    /// Returns the number of bytes read from the reader.
+    pub(crate) fn read(&self) -> u64 {
+        self.read.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of bytes written to the writer.
+    pub(crate) fn written(&self) -> u64 {
+        self.written.load(Ordering::Acquire)
+    }
+
+    /// Records that `bytes` more bytes have been transferred.
+    fn add_transferred(&self, bytes: u64) {
+        // If someone would like to confirm the correctness of the ordering guarantees, that would
+        // be much appreciated.
+        let previous = self.transferred.fetch_add(bytes, Ordering::Release);
+        // Overflowing is the only way adding to the counter could make it go down
+        debug_assert!(
+            previous.checked_add(bytes).is_some(),
+            "transferred byte count decreased: {} + {} overflowed",
+            previous,
+            bytes
+        );
+        if let Some(timing) = &self.timing {
+            timing.record(&self.transferred);
+        }
+        if let Some(now) = self.since_created() {
+            self.last_progress.store(now, Ordering::Release);
+        }
+        self.notify_progress();
+    }
+
+    /// Marks the transfer as complete.
+    pub(crate) fn set_complete(&self) {
+        // Stop the clock first, so the running time of a complete transfer never changes
+        if let Some(timing) = &self.timing {
+            timing.finish();
+        }
+        self.complete.store(true, Ordering::Release);
+        // Release anything the hook holds on to, such as sockets, now there's nothing to wake
+        self.cancel_hook.lock().unwrap().take();
+        self.notify_progress();
+    }
+
+    /// Records that the transfer ended with `error`. Must be called before `set_complete`.
+    pub(crate) fn set_failed(&self, error: &TransferError) {
+        *self.error.lock().unwrap() = Some(error.to_string());
+        self.failed.store(true, Ordering::Release);
+    }
+
+    /// Returns a description of the error the transfer ended with, if any.
+    pub(crate) fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Records the digest of the data transferred. Must be called before `set_complete`.
+    pub(crate) fn set_digest(&self, digest: Vec<u8>) {
+        *self.digest.lock().unwrap() = Some(digest);
+    }
+
+    /// Returns the digest of the data transferred, if one was calculated.
+    #[cfg(feature = "digest")]
+    pub(crate) fn digest(&self) -> Option<Vec<u8>> {
+        self.digest.lock().unwrap().clone()
+    }
+
+    /// Pauses the transfer, stopping its clock, if it isn't already paused.
+    pub(crate) fn pause(&self) {
+        if !self.paused.swap(true, Ordering::AcqRel) {
+            if let Some(timing) = &self.timing {
+                timing.pause();
+            }
+        }
+    }
+
+    /// Resumes the transfer, if it's paused.
+    pub(crate) fn resume(&self) {
+        if self.paused.swap(false, Ordering::AcqRel) {
+            if let Some(timing) = &self.timing {
+                timing.resume();
+            }
+            // Time spent paused doesn't count towards a stall
+            if let Some(now) = self.since_created() {
+                self.last_progress.store(now, Ordering::Release);
+            }
+            self.notify_progress();
+        }
+    }
+
+    /// Returns whether the transfer is paused.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Blocks until `done` returns `true`, which is checked each time the transfer is woken up, as
+    /// it is when it's resumed or cancelled, or until `deadline` passes if there is one.
+    pub(crate) fn wait_until(&self, deadline: Option<Instant>, done: impl Fn() -> bool) {
+        let mut guard = self.progress_lock.lock().unwrap();
+        while !done() {
+            guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    self.progress_made
+                        .wait_timeout(guard, deadline - now)
+                        .unwrap()
+                        .0
+                }
+                None => self.progress_made.wait(guard).unwrap(),
+            };
+        }
+    }
+
+    /// Records that a chunk of `len` bytes has been read by a pipelined transfer's reading thread,
+    /// to be written by its writing thread.
+    pub(crate) fn push_queued(&self, len: u64) {
+        self.queued.fetch_add(1, Ordering::AcqRel);
+        let bytes = self.queued_bytes.fetch_add(len, Ordering::AcqRel) + len;
+        self.queue_high_water.fetch_max(bytes, Ordering::AcqRel);
+    }
+
+    /// Records that the writing thread has taken a chunk of `len` bytes off the queue.
+    pub(crate) fn pop_queued(&self, len: u64) {
+        self.queued.fetch_sub(1, Ordering::AcqRel);
+        self.queued_bytes.fetch_sub(len, Ordering::AcqRel);
+    }
+
+    /// Empties the queue once a pipelined transfer has stopped, leaving its high-water mark.
+    pub(crate) fn clear_queued(&self) {
+        self.queued.store(0, Ordering::Release);
+        self.queued_bytes.store(0, Ordering::Release);
+    }
+
+    /// Asks the transfer to stop before copying its next chunk.
+    pub(crate) fn request_cancel(&self) {
+        self.cancel_requested.store(true, Ordering::Release);
+        if let Some(hook) = &*self.cancel_hook.lock().unwrap() {
+            hook();
+        }
+        // Wake the transfer up if it's paused, or waiting for its schedule
+        self.notify_progress();
+    }
+
+    /// Sets `hook` to be called each time cancellation is requested, until the transfer completes.
+    /// Must be called before the transfer can be cancelled.
+    pub(crate) fn set_cancel_hook(&self, hook: impl Fn() + Send + Sync + 'static) {
+        *self.cancel_hook.lock().unwrap() = Some(Box::new(hook));
+    }
+
+    pub(crate) fn notify_progress(&self) {
+        // Taking the lock ensures a waiter can't miss this between checking its condition and
+        // starting to wait
+        let _guard = self.progress_lock.lock().unwrap();
+        self.progress_made.notify_all();
+    }
+
+    /// Blocks until the transfer completes.
+    pub(crate) fn wait_complete(&self) {
+        let mut guard = self.progress_lock.lock().unwrap();
+        while !self.is_complete() {
+            guard = self.progress_made.wait(guard).unwrap();
+        }
+    }
+
+    /// Blocks until the transfer completes, or `timeout` elapses. Returns whether the transfer is
+    /// complete.
+    pub(crate) fn wait_complete_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.progress_lock.lock().unwrap();
+        loop {
+            if self.is_complete() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            guard = self
+                .progress_made
+                .wait_timeout(guard, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Blocks until at least `threshold` bytes have been transferred, returning `true`, or until
+    /// the transfer completes without reaching it, returning `false`.
+    pub(crate) fn wait_for_transferred(&self, threshold: u64) -> bool {
+        let mut guard = self.progress_lock.lock().unwrap();
+        loop {
+            // Check completion first, so we don't miss bytes transferred just before completing
+            let complete = self.is_complete();
+            if self.transferred() >= threshold {
+                return true;
+            } else if complete {
+                return false;
+            }
+            guard = self.progress_made.wait(guard).unwrap();
+        }
+    }
+
+    /// Returns whether timing is being tracked for this transfer.
+    pub(crate) fn is_timed(&self) -> bool {
+        self.timing.is_some()
+    }
+
+    /// Returns the time elapsed since the transfer started, or zero if timing isn't being tracked.
+    pub(crate) fn running_time(&self) -> Duration {
+        self.timing.as_ref().map_or(Duration::ZERO, Timing::elapsed)
+    }
+
+    /// Records that the transfer spent `duration` paused outside its schedule.
+    pub(crate) fn add_parked(&self, duration: Duration) {
+        self.parked
+            .fetch_add(duration.as_nanos() as u64, Ordering::Release);
+    }
+
+    /// Returns the running time, less any time spent paused outside the transfer's schedule.
+    pub(crate) fn active_time(&self) -> Duration {
+        let parked = Duration::from_nanos(self.parked.load(Ordering::Acquire));
+        self.running_time().saturating_sub(parked)
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer, or 0 if timing isn't being
+    /// tracked.
+    pub(crate) fn speed(&self) -> f64 {
+        if !self.is_timed() {
+            return 0.0;
+        }
+        self.transferred_since_start() as f64 / self.running_time().as_secs_f64()
+    }
+
+    /// Takes a snapshot of the transfer's statistics.
+    ///
+    /// Each value is read exactly once, and the speed is derived from those same values rather
+    /// than sampled separately, so the statistics in the snapshot always agree with each other.
+    pub(crate) fn snapshot(&self) -> ProgressSnapshot {
+        // Load completion first, so a complete snapshot always has the final byte count
+        let complete = self.is_complete();
+        let size = self.size();
+        // Load the offset first, so it's never more than the number transferred
+        let offset = self.offset.load(Ordering::Acquire);
+        let transferred = self.transferred();
+        let elapsed = self.running_time();
+        let speed = if elapsed.is_zero() {
+            0
+        } else {
+            (transferred.saturating_sub(offset) as f64 / elapsed.as_secs_f64()).round() as u64
+        };
+        ProgressSnapshot {
+            id: self.id,
+            name: self.name.clone(),
+            transferred,
+            size,
+            elapsed,
+            speed,
+            complete,
+        }
+    }
+
+    /// Makes the transfer's running time start from now, discarding any speed samples so far.
+    pub(crate) fn restart_timer(&self) {
+        if let Some(timing) = &self.timing {
+            timing.restart(self.transferred());
+        }
+        self.parked.store(0, Ordering::Release);
+    }
+
+    /// Returns the average number of bytes transferred per chunk, or `None` if no chunks have been
+    /// transferred yet.
+    pub(crate) fn average_chunk_size(&self) -> Option<u64> {
+        // Load the chunks first, so the bytes of every chunk counted have been counted too
+        let chunks = self.chunks.load(Ordering::Acquire);
+        if chunks == 0 {
+            return None;
+        }
+        Some(self.transferred_since_start() / chunks)
+    }
+
+    /// Returns the length of the largest chunk read, or `None` if no chunks have been read yet.
+    pub(crate) fn max_chunk_size(&self) -> Option<u64> {
+        match self.max_chunk.load(Ordering::Acquire) {
+            0 => None,
+            len => Some(len),
+        }
+    }
+
+    /// Zeroes the transfer's statistics, so they are measured from now on, as if the transfer had
+    /// just started.
+    pub(crate) fn reset(&self) {
+        match &self.timing {
+            Some(timing) => timing.reset(&self.transferred),
+            None => self.transferred.store(0, Ordering::Release),
+        }
+        self.offset.store(0, Ordering::Release);
+        self.read.store(0, Ordering::Release);
+        self.written.store(0, Ordering::Release);
+        self.chunks.store(0, Ordering::Release);
+        self.max_chunk.store(0, Ordering::Release);
+        self.forced_flushes.store(0, Ordering::Release);
+        self.parked.store(0, Ordering::Release);
+        self.segment.lock().unwrap().1 = 0;
+    }
+
+    /// Records that the item of a [`Sequence`][crate::Sequence], or the file of a
+    /// [`DirTransfer`][crate::DirTransfer], at `index` has started being read.
+    pub(crate) fn start_segment(&self, index: usize) {
+        let transferred = self.transferred();
+        *self.segment.lock().unwrap() = (index, transferred);
+    }
+
+    /// Returns the index of the item of a [`Sequence`][crate::Sequence], or the file of a
+    /// [`DirTransfer`][crate::DirTransfer], being read, and the number of bytes transferred from
+    /// it.
+    pub(crate) fn segment(&self) -> (usize, u64) {
+        let (index, start) = *self.segment.lock().unwrap();
+        let transferred = self.transferred();
+        (index, transferred.saturating_sub(start))
+    }
+
+    /// Calls `read`, adding the time it takes to the time spent reading, if timing is being
+    /// tracked.
+    pub(crate) fn time_read<T>(&self, read: impl FnOnce() -> T) -> T {
+        match &self.timing {
+            Some(timing) => timing.time(&timing.reading, read),
+            None => read(),
+        }
+    }
+
+    /// Calls `write`, adding the time it takes to the time spent writing, if timing is being
+    /// tracked.
+    pub(crate) fn time_write<T>(&self, write: impl FnOnce() -> T) -> T {
+        match &self.timing {
+            Some(timing) => timing.time(&timing.writing, write),
+            None => write(),
+        }
+    }
+
+    /// Calls `flush`, adding the time it takes to the time spent flushing, if timing is being
+    /// tracked.
+    pub(crate) fn time_flush<T>(&self, flush: impl FnOnce() -> T) -> T {
+        match &self.timing {
+            Some(timing) => timing.time(&timing.flushing, flush),
+            None => flush(),
+        }
+    }
+
+    /// Returns the total time spent flushing, or zero if timing isn't being tracked.
+    pub(crate) fn flush_time(&self) -> Duration {
+        self.timing.as_ref().map_or(Duration::ZERO, |timing| {
+            Duration::from_nanos(timing.flushing.load(Ordering::Acquire))
+        })
+    }
+
+    /// Returns the total time spent reading and writing, excluding flushing, or `None` if timing
+    /// isn't being tracked.
+    pub(crate) fn read_write_time(&self) -> Option<(Duration, Duration)> {
+        self.timing.as_ref().map(|timing| {
+            (
+                Duration::from_nanos(timing.reading.load(Ordering::Acquire)),
+                Duration::from_nanos(timing.writing.load(Ordering::Acquire)),
+            )
+        })
+    }
+
+    /// Returns the samples of the transfer's progress kept for
+    /// [`Transfer::speed_history`][crate::Transfer::speed_history], oldest first, or none if timing
+    /// isn't being tracked.
+    pub(crate) fn speed_history(&self) -> Vec<(Duration, u64)> {
+        self.timing.as_ref().map_or_else(Vec::new, Timing::history)
+    }
+
+    /// Returns the speed, in bytes per second, measured over the last
+    /// [`speed_window`][Self::speed_window], or 0 if timing isn't being tracked.
+    pub(crate) fn windowed_speed(&self) -> f64 {
+        match &self.timing {
+            Some(timing) => timing.speed(self.transferred()),
+            None => 0.0,
+        }
+    }
+
+    /// Returns the length of the window over which the windowed speed is measured, or zero if
+    /// timing isn't being tracked.
+    pub(crate) fn speed_window(&self) -> Duration {
+        self.timing
+            .as_ref()
+            .map_or(Duration::ZERO, |timing| timing.window)
+    }
+}
+
+/// Marks a transfer as failed and complete if the thread performing it panics while this is
+/// alive, so that nothing waits for it forever.
+pub(crate) struct CompleteOnPanic<'a>(pub(crate) &'a TransferState);
+
+impl Drop for CompleteOnPanic<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.set_failed(&TransferError::Panicked(None));
+            self.0.set_complete();
+        }
+    }
+}
+
+/// Keeps track of when a transfer started, and samples of its progress over the last `window`.
+struct Timing {
+    /// The instant all other timestamps are measured relative to.
+    epoch: Instant,
+    /// Nanoseconds since `epoch` at which the transfer started.
+    start: AtomicU64,
+    /// Nanoseconds since `epoch` at which the last sample was recorded.
+    last_sample: AtomicU64,
+    /// The length of the window over which samples are kept.
+    window: Duration,
+    /// `(nanoseconds since epoch, bytes transferred)` pairs, oldest first.
+    samples: Mutex<VecDeque<(u64, u64)>>,
+    /// The minimum time between two samples being recorded in the history.
+    history_interval: Duration,
+    /// The most samples kept in the history.
+    history_len: usize,
+    /// Nanoseconds since `epoch` at which the last sample was recorded in the history.
+    last_history: AtomicU64,
+    /// Samples like `samples`, but further apart, and bounded in number rather than age.
+    history: Mutex<VecDeque<(u64, u64)>>,
+    /// Nanoseconds spent blocked in calls to the reader.
+    reading: AtomicU64,
+    /// Nanoseconds spent blocked in calls to the writer, other than flushes.
+    writing: AtomicU64,
+    /// Nanoseconds spent blocked flushing the writer.
+    flushing: AtomicU64,
+    /// Nanoseconds since `epoch` at which the transfer was paused, or `u64::MAX` if it isn't.
+    paused_at: AtomicU64,
+    /// Nanoseconds since `epoch` at which the transfer completed, or `u64::MAX` if it hasn't.
+    finished_at: AtomicU64,
+}
+
+impl Timing {
+    fn new(config: &TransferConfig, transferred: u64) -> Self {
+        let mut samples = VecDeque::new();
+        samples.push_back((0, transferred));
+        let history = samples.iter().copied().take(config.history_len).collect();
+        Self {
+            epoch: Instant::now(),
+            start: AtomicU64::new(0),
+            last_sample: AtomicU64::new(0),
+            window: config.speed_window,
+            samples: Mutex::new(samples),
+            history_interval: config.history_interval,
+            history_len: config.history_len,
+            last_history: AtomicU64::new(0),
+            history: Mutex::new(history),
+            reading: AtomicU64::new(0),
+            writing: AtomicU64::new(0),
+            flushing: AtomicU64::new(0),
+            paused_at: AtomicU64::new(u64::MAX),
+            finished_at: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64
+    }
+
+    /// Calls `f`, adding the time it takes to `total`.
+    fn time<T>(&self, total: &AtomicU64, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        total.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Release);
+        result
+    }
+
+    /// Returns the time elapsed since the start, not counting time spent paused, or the time
+    /// taken if the transfer is complete.
+    fn elapsed(&self) -> Duration {
+        // The clock stops while paused, and for good once the transfer completes
+        let now = self
+            .now()
+            .min(self.paused_at.load(Ordering::Acquire))
+            .min(self.finished_at.load(Ordering::Acquire));
+        Duration::from_nanos(now.saturating_sub(self.start.load(Ordering::Acquire)))
+    }
+
+    /// Stops the clock for good. The time at which the transfer was paused counts as its
+    /// completion time, if it was paused at the time.
+    fn finish(&self) {
+        let now = self.now().min(self.paused_at.load(Ordering::Acquire));
+        self.finished_at.store(now, Ordering::Release);
+    }
+
+    fn pause(&self) {
+        self.paused_at.store(self.now(), Ordering::Release);
+    }
+
+    fn resume(&self) {
+        let now = self.now();
+        let paused_at = self.paused_at.swap(u64::MAX, Ordering::AcqRel);
+        // Move the start forward by the length of the pause, so it doesn't count
+        self.start
+            .fetch_add(now.saturating_sub(paused_at), Ordering::AcqRel);
+    }
+
+    fn restart(&self, transferred: u64) {
+        let now = self.now();
+        let mut samples = self.samples.lock().unwrap();
+        samples.clear();
+        samples.push_back((now, transferred));
+        self.last_sample.store(now, Ordering::Relaxed);
+        self.start.store(now, Ordering::Release);
+    }
+
+    /// Zeroes `transferred` and restarts the timer. The counter is zeroed while holding the lock
+    /// on the samples, so that a sample taken before the reset can't be recorded after it.
+    fn reset(&self, transferred: &AtomicU64) {
+        let now = self.now();
+        let mut samples = self.samples.lock().unwrap();
+        transferred.store(0, Ordering::Release);
+        samples.clear();
+        samples.push_back((now, 0));
+        let mut history = self.history.lock().unwrap();
+        history.clear();
+        if self.history_len > 0 {
+            history.push_back((now, 0));
+        }
+        self.last_history.store(now, Ordering::Relaxed);
+        self.reading.store(0, Ordering::Release);
+        self.writing.store(0, Ordering::Release);
+        self.flushing.store(0, Ordering::Release);
+        self.last_sample.store(now, Ordering::Relaxed);
+        self.start.store(now, Ordering::Release);
+    }
+
+    /// Pushes a sample of `transferred` into the window, unless one was recorded very recently.
+    fn record(&self, transferred: &AtomicU64) {
+        let now = self.now();
+        let last = self.last_sample.load(Ordering::Relaxed);
+        if now.saturating_sub(last) < SAMPLE_INTERVAL.as_nanos() as u64 {
+            return;
+        }
+        self.last_sample.store(now, Ordering::Relaxed);
+        let mut samples = self.samples.lock().unwrap();
+        let transferred = transferred.load(Ordering::Acquire);
+        samples.push_back((now, transferred));
+        let cutoff = now.saturating_sub(self.window.as_nanos() as u64);
+        while samples.front().is_some_and(|&(time, _)| time < cutoff) {
+            samples.pop_front();
+        }
+        // Still holding the lock on the samples, so the history can't be reset under us
+        let last_history = self.last_history.load(Ordering::Relaxed);
+        if self.history_len > 0
+            && now.saturating_sub(last_history) >= self.history_interval.as_nanos() as u64
+        {
+            self.last_history.store(now, Ordering::Relaxed);
+            let mut history = self.history.lock().unwrap();
+            if history.len() == self.history_len {
+                history.pop_front();
+            }
+            history.push_back((now, transferred));
+        }
+    }
+
+    /// Returns the samples in the history, as `(time since epoch, bytes transferred)` pairs.
+    fn history(&self) -> Vec<(Duration, u64)> {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&(time, bytes)| (Duration::from_nanos(time), bytes))
+            .collect()
+    }
+
+    /// Returns the speed, in bytes per second, over the window, given the current number of bytes
+    /// transferred.
+    fn speed(&self, transferred: u64) -> f64 {
+        let now = self.now();
+        let cutoff = now.saturating_sub(self.window.as_nanos() as u64);
+        let samples = self.samples.lock().unwrap();
+        match samples.iter().find(|&&(time, _)| time >= cutoff) {
+            Some(&(time, bytes)) if now > time => {
+                let elapsed = Duration::from_nanos(now - time).as_secs_f64();
+                transferred.saturating_sub(bytes) as f64 / elapsed
+            }
+            // Either nothing has happened within the window, or no time has passed at all.
+            _ => 0.0,
+        }
+    }
+}
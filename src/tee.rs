@@ -0,0 +1,145 @@
+use std::io::{self, prelude::*};
+
+use crate::Transfer;
+
+/// A writer which writes everything to several writers at once, as used by
+/// [`Transfer::tee`].
+///
+/// If one of the writers fails, it's left out of any further writes, and its error is recorded,
+/// while the others carry on. Writing only fails once every writer has failed. To mix writers of
+/// different types, box them as `Box<dyn Write + Send>`.
+#[derive(Debug)]
+pub struct Tee<W> {
+    writers: Vec<W>,
+    /// The error each writer failed with, if any.
+    errors: Vec<Option<io::Error>>,
+}
+
+impl<W: Write> Tee<W> {
+    /// Creates a new `Tee` writing to each of `writers`.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Tee;
+    /// use std::io::Write;
+    /// let mut tee = Tee::new(vec![Vec::new(), Vec::new()]);
+    /// tee.write_all(b"Hello, world!")?;
+    /// for writer in tee.into_inner() {
+    ///     assert_eq!(writer, b"Hello, world!");
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn new(writers: Vec<W>) -> Self {
+        let errors = writers.iter().map(|_| None).collect();
+        Self { writers, errors }
+    }
+
+    /// Returns the writers, in the order they were given.
+    pub fn writers(&self) -> &[W] {
+        &self.writers
+    }
+
+    /// Returns the error the writer at `index` failed with, or `None` if it hasn't failed.
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn error(&self, index: usize) -> Option<&io::Error> {
+        self.errors[index].as_ref()
+    }
+
+    /// Returns the number of writers which haven't failed.
+    pub fn healthy(&self) -> usize {
+        self.errors.iter().filter(|error| error.is_none()).count()
+    }
+
+    /// Consumes the `Tee`, returning the writers, in the order they were given, whether or not
+    /// they failed.
+    pub fn into_inner(self) -> Vec<W> {
+        self.writers
+    }
+
+    /// Consumes the `Tee`, returning each writer, or the error it failed with, in the order they
+    /// were given.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Tee;
+    /// use std::io::{self, Write};
+    /// let writers: Vec<Box<dyn Write + Send>> = vec![
+    ///     Box::new(Vec::new()),
+    ///     // A writer with room for nothing
+    ///     Box::new(io::Cursor::new(&mut [][..])),
+    /// ];
+    /// let mut tee = Tee::new(writers);
+    /// tee.write_all(b"Hello, world!")?;
+    /// let results = tee.into_results();
+    /// assert!(results[0].is_ok());
+    /// assert_eq!(results[1].as_ref().err().unwrap().kind(), io::ErrorKind::WriteZero);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn into_results(self) -> Vec<io::Result<W>> {
+        self.writers
+            .into_iter()
+            .zip(self.errors)
+            .map(|(writer, error)| match error {
+                Some(error) => Err(error),
+                None => Ok(writer),
+            })
+            .collect()
+    }
+
+    /// Calls `f` on each writer which hasn't failed, recording any errors. Fails if every writer
+    /// has failed.
+    fn each(&mut self, mut f: impl FnMut(&mut W) -> io::Result<()>) -> io::Result<()> {
+        for (writer, error) in self.writers.iter_mut().zip(&mut self.errors) {
+            if error.is_none() {
+                if let Err(e) = f(writer) {
+                    *error = Some(e);
+                }
+            }
+        }
+        match self.errors.first() {
+            Some(Some(error)) if self.healthy() == 0 => Err(io::Error::new(
+                error.kind(),
+                format!("every writer of the tee failed, the first with: {}", error),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Every writer has to take all of the data, or they'd fall out of step
+        self.each(|writer| writer.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.each(Write::flush)
+    }
+}
+
+impl<R, W> Transfer<R, Tee<W>>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Creates and starts a new `Transfer` which copies from `reader` to each of `writers` at
+    /// once, through a [`Tee`].
+    ///
+    /// The transfer's progress counts the bytes read once, however many writers there are. If a
+    /// writer fails, the others carry on, and the transfer only fails once they all have, so the
+    /// returned `Tee` should be checked to see which of them succeeded.
+    /// # Example
+    /// ```
+    /// use transfer_progress::Transfer;
+    /// use std::io;
+    /// let transfer = Transfer::tee(io::Cursor::new(vec![1; 1024]), vec![Vec::new(), Vec::new()]);
+    /// let (_, tee) = transfer.finish()?;
+    /// for writer in tee.into_results() {
+    ///     assert_eq!(writer?, vec![1; 1024]);
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn tee(reader: R, writers: Vec<W>) -> Self {
+        Self::new(reader, Tee::new(writers))
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::{format, ProgressSnapshot};
+
+/// A value which can be substituted into a
+/// [display template][crate::TransferBuilder::display_template].
+#[derive(Clone, Copy)]
+enum Field {
+    Transferred,
+    Size,
+    Percent,
+    Speed,
+    Eta,
+    Elapsed,
+    Name,
+    Id,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "transferred" => Self::Transferred,
+            "size" => Self::Size,
+            "percent" => Self::Percent,
+            "speed" => Self::Speed,
+            "eta" => Self::Eta,
+            "elapsed" => Self::Elapsed,
+            "name" => Self::Name,
+            "id" => Self::Id,
+            _ => return None,
+        })
+    }
+}
+
+/// A piece of a parsed template.
+enum Piece<'a> {
+    Literal(&'a str),
+    /// A field, with the number of decimal places to show it to, if given.
+    Field(Field, Option<usize>),
+}
+
+/// Splits `template` into its pieces, or returns a description of what's wrong with it.
+fn parse(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        if i > 0 {
+            pieces.push(Piece::Literal(&rest[..i]));
+        }
+        let brace = &rest[i..i + 1];
+        rest = &rest[i + 1..];
+        // Doubled braces stand for themselves
+        if let Some(after) = rest.strip_prefix(brace) {
+            pieces.push(Piece::Literal(brace));
+            rest = after;
+            continue;
+        }
+        if brace == "}" {
+            return Err("unmatched `}` in display template".to_string());
+        }
+        let end = rest
+            .find('}')
+            .ok_or_else(|| "unclosed `{` in display template".to_string())?;
+        let (name, spec) = match rest[..end].split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (&rest[..end], None),
+        };
+        let field = Field::from_name(name)
+            .ok_or_else(|| format!("unknown field `{}` in display template", name))?;
+        let precision = match (field, spec) {
+            (_, None) => None,
+            (Field::Percent, Some(spec)) => match spec.strip_prefix('.').map(str::parse) {
+                Some(Ok(precision)) => Some(precision),
+                _ => return Err(format!("invalid format `{}` for `percent`", spec)),
+            },
+            (_, Some(_)) => return Err(format!("`{}` doesn't take a format", name)),
+        };
+        pieces.push(Piece::Field(field, precision));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Literal(rest));
+    }
+    Ok(pieces)
+}
+
+/// Checks that `template` is a valid display template, returning a description of what's wrong
+/// with it if not.
+pub(crate) fn validate(template: &str) -> Result<(), String> {
+    parse(template).map(|_| ())
+}
+
+/// Writes `snapshot` of the transfer called `name` to `f` laid out according to `template`, which
+/// must be valid. Sizes are in SI units if `si` is `true`, or IEC units otherwise, and the speed
+/// is in bits if `in_bits` is `true`.
+pub(crate) fn write(
+    f: &mut fmt::Formatter,
+    template: &str,
+    snapshot: &ProgressSnapshot,
+    name: Option<&str>,
+    si: bool,
+    in_bits: bool,
+) -> fmt::Result {
+    let pieces = parse(template).map_err(|_| fmt::Error)?;
+    for piece in pieces {
+        let (field, precision) = match piece {
+            Piece::Literal(text) => {
+                f.write_str(text)?;
+                continue;
+            }
+            Piece::Field(field, precision) => (field, precision),
+        };
+        match field {
+            Field::Transferred => f.write_str(&format::bytes(snapshot.transferred, si))?,
+            Field::Size => match snapshot.size {
+                Some(size) => f.write_str(&format::bytes(size, si))?,
+                None => f.write_str("?")?,
+            },
+            Field::Percent => match snapshot.fraction_transferred() {
+                Some(fraction) => {
+                    write!(f, "{:.*}", precision.unwrap_or(1), fraction * 100.0)?;
+                }
+                None => f.write_str("?")?,
+            },
+            Field::Speed => f.write_str(&format::speed(snapshot.speed, si, in_bits))?,
+            Field::Eta => match snapshot.eta() {
+                Some(eta) => f.write_str(&format::clock(eta))?,
+                None => f.write_str("--:--")?,
+            },
+            Field::Elapsed => f.write_str(&format::clock(snapshot.elapsed))?,
+            Field::Name => match name {
+                Some(name) => f.write_str(name)?,
+                None => write!(f, "{}", snapshot.id)?,
+            },
+            Field::Id => write!(f, "{}", snapshot.id)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,475 @@
+use std::{
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+use crate::{state::TransferState, ProgressHandle, TransferConfig, TransferError};
+
+/// The number of entries in each driver's submission queue.
+const RING_ENTRIES: u32 = 256;
+/// The `user_data` of the operation waiting for the driver to be woken up.
+const WAKE: u64 = u64::MAX;
+/// The `user_data` of operations cancelling the operations of cancelled jobs.
+const CANCEL: u64 = u64::MAX - 1;
+/// An offset telling the kernel to use, and advance, the file's own position.
+const CURRENT_POSITION: u64 = u64::MAX;
+
+/// Drives any number of [`UringTransfer`]s from a single thread, using Linux's `io_uring`
+/// interface. Only available with the `io-uring` feature, on Linux.
+///
+/// Rather than a thread per transfer, blocked in reads and writes, the driver's thread submits
+/// every transfer's reads and writes to the kernel together, and carries each of them on as its
+/// operations complete. This scales to thousands of concurrent transfers.
+///
+/// Dropping the driver doesn't stop its transfers: its thread exits once they have all completed.
+/// # Example
+/// ```no_run
+/// use transfer_progress::UringDriver;
+/// use std::fs::File;
+/// let driver = UringDriver::new()?;
+/// let transfers = (0..100)
+///     .map(|i| {
+///         let reader = File::open(format!("in{}.bin", i))?;
+///         let writer = File::create(format!("out{}.bin", i))?;
+///         Ok(driver.transfer(reader, writer))
+///     })
+///     .collect::<std::io::Result<Vec<_>>>()?;
+/// for transfer in transfers {
+///     transfer.finish()?;
+/// }
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub struct UringDriver {
+    sender: Option<Sender<Job>>,
+    /// An eventfd, which the driver's thread waits on, written to when there's a new job.
+    wake: Arc<OwnedFd>,
+}
+
+impl UringDriver {
+    /// Creates a new `UringDriver`, starting its thread.
+    ///
+    /// Fails if the kernel doesn't support `io_uring`, or it's disabled.
+    pub fn new() -> io::Result<Self> {
+        let ring = IoUring::new(RING_ENTRIES)?;
+        // SAFETY: `eventfd` has no preconditions. A non-negative result is a new file descriptor,
+        // which nothing else owns.
+        let wake = unsafe {
+            let fd = libc::eventfd(0, libc::EFD_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Arc::new(OwnedFd::from_raw_fd(fd))
+        };
+        let (sender, receiver) = mpsc::channel();
+        let driver = Driver {
+            ring,
+            receiver,
+            wake: Arc::clone(&wake),
+            wake_buf: Box::new([0; 8]),
+            jobs: Vec::new(),
+        };
+        thread::spawn(move || driver.run());
+        Ok(Self {
+            sender: Some(sender),
+            wake,
+        })
+    }
+
+    /// Creates and starts a new [`UringTransfer`] from `reader` to `writer` on this driver.
+    ///
+    /// The reader and writer are read and written from their current positions, if they have
+    /// them, so they can be files, sockets or pipes. They're handed back by
+    /// [`finish`][UringTransfer::finish].
+    pub fn transfer<R, W>(&self, reader: R, writer: W) -> UringTransfer<R, W>
+    where
+        R: AsRawFd + Send + 'static,
+        W: AsRawFd + Send + 'static,
+    {
+        let config = TransferConfig::default();
+        let state = Arc::new(TransferState::new(&config, None));
+        state.set_cancel_hook({
+            // Wake the driver up, so it can cancel the job's operation, which may never complete
+            // otherwise
+            let wake_fd = Arc::clone(&self.wake);
+            move || wake(&wake_fd)
+        });
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let job = Job {
+            input: reader.as_raw_fd(),
+            output: writer.as_raw_fd(),
+            state: Arc::clone(&state),
+            buf: vec![0; config.buffer_size],
+            pending: None,
+            polling: false,
+            done: Box::new(move |result| {
+                let _ = sender.send(result.map(|()| (reader, writer)));
+            }),
+        };
+        if let Some(jobs) = &self.sender {
+            // The thread only exits once we're dropped, so this can't fail
+            let _ = jobs.send(job);
+        }
+        wake(&self.wake);
+        UringTransfer { state, receiver }
+    }
+}
+
+impl Drop for UringDriver {
+    fn drop(&mut self) {
+        // Let the thread see that there will be no more jobs
+        self.sender = None;
+        wake(&self.wake);
+    }
+}
+
+/// Wakes up a driver's thread by writing to its eventfd.
+fn wake(fd: &OwnedFd) {
+    let one = 1u64.to_ne_bytes();
+    // SAFETY: `fd` is a valid eventfd, and `one` is the 8 bytes eventfds expect.
+    unsafe { libc::write(fd.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+}
+
+/// Monitors the progress of a transfer performed by a [`UringDriver`]. Only available with the
+/// `io-uring` feature, on Linux.
+pub struct UringTransfer<R, W> {
+    state: Arc<TransferState>,
+    receiver: Receiver<Result<(R, W), TransferError>>,
+}
+
+impl<R, W> UringTransfer<R, W> {
+    /// Consumes the `UringTransfer`, blocking until the transfer is complete.
+    ///
+    /// If the transfer was successful, returns `Ok(reader, writer)`, otherwise returns the error.
+    /// # Example
+    /// ```
+    /// use transfer_progress::UringDriver;
+    /// use std::fs::{self, File};
+    /// let dir = std::env::temp_dir();
+    /// let (from, to) = (dir.join("uring-finish-from"), dir.join("uring-finish-to"));
+    /// let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    /// fs::write(&from, &data)?;
+    /// // `io_uring` may be unavailable, in which case there's nothing to test
+    /// if let Ok(driver) = UringDriver::new() {
+    ///     let transfer = driver.transfer(File::open(&from)?, File::create(&to)?);
+    ///     transfer.finish()?;
+    ///     assert_eq!(fs::read(&to)?, data);
+    ///     fs::remove_file(&to)?;
+    /// }
+    /// # fs::remove_file(from)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn finish(self) -> io::Result<(R, W)> {
+        self.receiver
+            .recv()
+            // The driver's thread only goes away without answering if it panicked
+            .unwrap_or(Err(TransferError::Panicked(None)))
+            .map_err(io::Error::from)
+    }
+
+    /// Asks the transfer to stop as soon as possible, even if it's waiting to read or write, after
+    /// which [`finish`][Self::finish] returns [`TransferError::Cancelled`].
+    /// # Example
+    /// ```
+    /// use transfer_progress::{TransferError, UringDriver};
+    /// use std::io;
+    /// use std::os::unix::net::UnixStream;
+    /// // A reader which never has anything to read
+    /// let (reader, _writer) = UnixStream::pair()?;
+    /// // `io_uring` may be unavailable, in which case there's nothing to test
+    /// if let Ok(driver) = UringDriver::new() {
+    ///     let transfer = driver.transfer(reader, std::fs::File::create("/dev/null")?);
+    ///     transfer.cancel();
+    ///     let error = transfer.finish().err().unwrap();
+    ///     let error = error.into_inner().unwrap().downcast::<TransferError>().unwrap();
+    ///     assert!(matches!(*error, TransferError::Cancelled));
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn cancel(&self) {
+        self.state.request_cancel();
+    }
+
+    /// Tests if the transfer is complete.
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    /// Returns the number of bytes transferred thus far between the reader and the writer.
+    pub fn transferred(&self) -> u64 {
+        self.state.transferred()
+    }
+
+    /// Returns the elapsed time since the transfer started.
+    pub fn running_time(&self) -> Duration {
+        self.state.running_time()
+    }
+
+    /// Returns the average speed, in bytes per second, of the transfer.
+    pub fn speed(&self) -> u64 {
+        self.state.speed().round() as u64
+    }
+
+    /// Returns a [`ProgressHandle`] for monitoring this transfer, which doesn't depend on the
+    /// types of its reader and writer.
+    pub fn progress_handle(&self) -> ProgressHandle {
+        ProgressHandle::new(Arc::clone(&self.state))
+    }
+}
+
+/// A transfer being performed by a driver's thread.
+struct Job {
+    input: RawFd,
+    output: RawFd,
+    state: Arc<TransferState>,
+    buf: Vec<u8>,
+    /// The range of `buf` still to be written, if the job is writing rather than reading.
+    pending: Option<(usize, usize)>,
+    /// Set while the job is waiting for its reader or writer to become ready, after it was
+    /// non-blocking and wasn't.
+    polling: bool,
+    /// Hands back the reader and writer, which own `input` and `output`, with the result.
+    done: Box<dyn FnOnce(Result<(), TransferError>) + Send>,
+}
+
+impl Job {
+    /// Returns the next operation the job needs performed.
+    fn next_operation(&mut self) -> squeue::Entry {
+        if self.polling {
+            let (fd, events) = match self.pending {
+                Some(_) => (self.output, libc::POLLOUT),
+                None => (self.input, libc::POLLIN),
+            };
+            return opcode::PollAdd::new(types::Fd(fd), events as u32).build();
+        }
+        match self.pending {
+            Some((start, end)) => opcode::Write::new(
+                types::Fd(self.output),
+                self.buf[start..].as_ptr(),
+                (end - start) as u32,
+            )
+            .offset(CURRENT_POSITION)
+            .build(),
+            None => opcode::Read::new(
+                types::Fd(self.input),
+                self.buf.as_mut_ptr(),
+                self.buf.len() as u32,
+            )
+            .offset(CURRENT_POSITION)
+            .build(),
+        }
+    }
+
+    /// Handles the `result` of the last operation, returning `Some` once the job is over.
+    fn complete_operation(&mut self, result: i32) -> Option<Result<(), TransferError>> {
+        if result < 0 {
+            // The operation may have failed because it was cancelled
+            if self.state.cancel_requested.load(Ordering::Acquire) {
+                return Some(Err(TransferError::Cancelled));
+            }
+            let error = io::Error::from_raw_os_error(-result);
+            return match error.kind() {
+                io::ErrorKind::Interrupted => None,
+                // Rather than retrying straight away, wait until it's worth retrying
+                io::ErrorKind::WouldBlock => {
+                    self.polling = true;
+                    None
+                }
+                _ => Some(Err(error.into())),
+            };
+        }
+        if self.polling {
+            // The reader or writer is ready, so retry the operation
+            self.polling = false;
+            return None;
+        }
+        let len = result as usize;
+        match self.pending {
+            None if len == 0 => return Some(Ok(())),
+            None => {
+                self.state.add_read(len as u64);
+                self.pending = Some((0, len));
+            }
+            Some((_, _)) if len == 0 => {
+                return Some(Err(io::Error::from(io::ErrorKind::WriteZero).into()))
+            }
+            Some((start, end)) => {
+                self.state.add_written(len as u64);
+                if start + len < end {
+                    self.pending = Some((start + len, end));
+                } else {
+                    self.pending = None;
+                    self.state.chunks.fetch_add(1, Ordering::Release);
+                }
+            }
+        }
+        if self.state.cancel_requested.load(Ordering::Acquire) {
+            return Some(Err(TransferError::Cancelled));
+        }
+        None
+    }
+
+    /// Records the result of the job in its state, and hands it back.
+    fn finish(self, result: Result<(), TransferError>) {
+        match &result {
+            Err(TransferError::Cancelled) => {
+                self.state.cancelled.store(true, Ordering::Release);
+                self.state.failed.store(true, Ordering::Release);
+            }
+            Err(e) => self.state.set_failed(e),
+            Ok(()) => {}
+        }
+        self.state.set_complete();
+        (self.done)(result);
+    }
+}
+
+/// The part of a [`UringDriver`] owned by its thread.
+struct Driver {
+    ring: IoUring,
+    receiver: Receiver<Job>,
+    wake: Arc<OwnedFd>,
+    /// The buffer the eventfd is read into.
+    wake_buf: Box<[u8; 8]>,
+    /// The jobs being performed, indexed by the `user_data` of their operations.
+    jobs: Vec<Option<Job>>,
+}
+
+impl Driver {
+    fn run(mut self) {
+        if let Err(e) = self.drive() {
+            // The ring is unusable, so fail everything
+            for mut job in self.jobs.drain(..).flatten() {
+                // The kernel may still write into the buffer, so it must never be freed
+                std::mem::forget(std::mem::take(&mut job.buf));
+                job.finish(Err(io::Error::new(e.kind(), e.to_string()).into()));
+            }
+            while let Ok(job) = self.receiver.try_recv() {
+                job.finish(Err(io::Error::new(e.kind(), e.to_string()).into()));
+            }
+        }
+    }
+
+    fn drive(&mut self) -> io::Result<()> {
+        self.submit_wake()?;
+        let mut accepting = true;
+        loop {
+            while accepting {
+                match self.receiver.try_recv() {
+                    Ok(job) => self.start(job)?,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => accepting = false,
+                }
+            }
+            if !accepting && self.jobs.iter().all(Option::is_none) {
+                return Ok(());
+            }
+            self.ring.submit_and_wait(1)?;
+            let completed: Vec<_> = self
+                .ring
+                .completion()
+                .map(|entry| (entry.user_data(), entry.result()))
+                .collect();
+            for (user_data, result) in completed {
+                if user_data == WAKE {
+                    self.submit_wake()?;
+                    self.cancel_requested()?;
+                    continue;
+                } else if user_data == CANCEL {
+                    // The cancelled operation completes, or already has, on its own
+                    continue;
+                }
+                let index = user_data as usize;
+                let job = match &mut self.jobs[index] {
+                    Some(job) => job,
+                    None => continue,
+                };
+                match job.complete_operation(result) {
+                    Some(result) => {
+                        if let Some(job) = self.jobs[index].take() {
+                            job.finish(result);
+                        }
+                    }
+                    None => self.submit(index)?,
+                }
+            }
+        }
+    }
+
+    /// Adds `job`, and submits its first operation.
+    fn start(&mut self, job: Job) -> io::Result<()> {
+        let index = match self.jobs.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.jobs.push(None);
+                self.jobs.len() - 1
+            }
+        };
+        if job.state.cancel_requested.load(Ordering::Acquire) {
+            job.finish(Err(TransferError::Cancelled));
+            return Ok(());
+        }
+        self.jobs[index] = Some(job);
+        self.submit(index)
+    }
+
+    /// Submits the next operation of the job at `index`.
+    fn submit(&mut self, index: usize) -> io::Result<()> {
+        let entry = match &mut self.jobs[index] {
+            Some(job) => job.next_operation().user_data(index as u64),
+            None => return Ok(()),
+        };
+        self.push(&entry)
+    }
+
+    /// Submits a cancellation of the operation of each job which has been asked to stop, so that
+    /// it completes straight away, even if it would otherwise wait indefinitely.
+    fn cancel_requested(&mut self) -> io::Result<()> {
+        for index in 0..self.jobs.len() {
+            let cancelled = match &self.jobs[index] {
+                Some(job) => job.state.cancel_requested.load(Ordering::Acquire),
+                None => false,
+            };
+            if cancelled {
+                let entry = opcode::AsyncCancel::new(index as u64)
+                    .build()
+                    .user_data(CANCEL);
+                self.push(&entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Submits a read of the eventfd, which completes when the driver is woken up.
+    fn submit_wake(&mut self) -> io::Result<()> {
+        let entry = opcode::Read::new(
+            types::Fd(self.wake.as_raw_fd()),
+            self.wake_buf.as_mut_ptr(),
+            self.wake_buf.len() as u32,
+        )
+        .build()
+        .user_data(WAKE);
+        self.push(&entry)
+    }
+
+    fn push(&mut self, entry: &squeue::Entry) -> io::Result<()> {
+        loop {
+            // SAFETY: The buffers and file descriptors of every operation stay valid until it
+            // completes: buffers belong to jobs, or the driver, which outlive their operations,
+            // and file descriptors to the readers and writers jobs own, or the driver.
+            if unsafe { self.ring.submission().push(entry) }.is_ok() {
+                return Ok(());
+            }
+            // The queue is full, so hand what's in it to the kernel to make room
+            self.ring.submit()?;
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::any::Any;
+#[cfg(target_os = "linux")]
+use std::{
+    fs::File,
+    io,
+    net::TcpStream,
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+};
+
+/// The most bytes copied by a single system call, so that progress is updated regularly.
+#[cfg(target_os = "linux")]
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Copies from a reader to a writer inside the kernel, without reading the data into a buffer, as
+/// enabled by [`TransferBuilder::zero_copy`][crate::TransferBuilder::zero_copy].
+#[cfg(target_os = "linux")]
+pub(crate) struct ZeroCopy {
+    input: RawFd,
+    output: RawFd,
+    method: Method,
+    /// The number of bytes taken from the reader and left in the pipe when splicing them into the
+    /// writer failed.
+    stranded: usize,
+}
+
+/// Copying inside the kernel is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub(crate) enum ZeroCopy {}
+
+/// The system call used to copy, depending on the kinds of file the reader and writer are.
+#[cfg(target_os = "linux")]
+enum Method {
+    /// From a file to a file.
+    CopyFileRange,
+    /// From a file to a socket.
+    SendFile,
+    /// From a socket, through a pipe, as `[read end, write end]`.
+    Splice([RawFd; 2]),
+}
+
+/// The kinds of file the kernel can copy between.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    File,
+    Socket,
+}
+
+#[cfg(target_os = "linux")]
+fn file_descriptor(io: &dyn Any) -> Option<(RawFd, Kind)> {
+    if let Some(file) = io.downcast_ref::<File>() {
+        Some((file.as_raw_fd(), Kind::File))
+    } else if let Some(stream) = io.downcast_ref::<TcpStream>() {
+        Some((stream.as_raw_fd(), Kind::Socket))
+    } else {
+        io.downcast_ref::<UnixStream>()
+            .map(|stream| (stream.as_raw_fd(), Kind::Socket))
+    }
+}
+
+/// Converts the result of a system call returning a byte count into an [`io::Result`].
+#[cfg(target_os = "linux")]
+fn check(result: isize) -> io::Result<usize> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result as usize)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ZeroCopy {
+    /// Returns a `ZeroCopy` from `reader` to `writer` if they're both a [`File`], [`TcpStream`] or
+    /// [`UnixStream`].
+    ///
+    /// The reader and writer must outlive the `ZeroCopy`.
+    pub(crate) fn for_pair(reader: &dyn Any, writer: &dyn Any) -> Option<Self> {
+        let (input, input_kind) = file_descriptor(reader)?;
+        let (output, output_kind) = file_descriptor(writer)?;
+        let method = match (input_kind, output_kind) {
+            (Kind::File, Kind::File) => Method::CopyFileRange,
+            (Kind::File, Kind::Socket) => Method::SendFile,
+            (Kind::Socket, _) => {
+                let mut pipe = [0; 2];
+                // SAFETY: `pipe` has room for the two file descriptors.
+                if unsafe { libc::pipe2(pipe.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+                    return None;
+                }
+                Method::Splice(pipe)
+            }
+        };
+        Some(Self {
+            input,
+            output,
+            method,
+            stranded: 0,
+        })
+    }
+
+    /// Copies the next chunk, returning its length, or 0 at the end of the reader.
+    pub(crate) fn copy_chunk(&mut self) -> io::Result<usize> {
+        // SAFETY: The file descriptors belong to the reader and writer, which outlive us, or to
+        // our pipe. Null offsets make the kernel use and update the files' own offsets.
+        unsafe {
+            match self.method {
+                Method::CopyFileRange => check(libc::copy_file_range(
+                    self.input,
+                    std::ptr::null_mut(),
+                    self.output,
+                    std::ptr::null_mut(),
+                    CHUNK_SIZE,
+                    0,
+                )),
+                Method::SendFile => check(libc::sendfile(
+                    self.output,
+                    self.input,
+                    std::ptr::null_mut(),
+                    CHUNK_SIZE,
+                )),
+                Method::Splice([pipe_read, pipe_write]) => {
+                    let len = check(libc::splice(
+                        self.input,
+                        std::ptr::null_mut(),
+                        pipe_write,
+                        std::ptr::null_mut(),
+                        CHUNK_SIZE,
+                        libc::SPLICE_F_MOVE,
+                    ))?;
+                    // Everything put in the pipe has to come out again before we return
+                    let mut remaining = len;
+                    while remaining > 0 {
+                        match check(libc::splice(
+                            pipe_read,
+                            std::ptr::null_mut(),
+                            self.output,
+                            std::ptr::null_mut(),
+                            remaining,
+                            libc::SPLICE_F_MOVE,
+                        )) {
+                            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                            Ok(written) => remaining -= written,
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                            // The writer can't be spliced into at all, so leave the chunk for
+                            // `take_stranded`
+                            Err(e) if remaining == len && Self::is_unsupported(&e) => {
+                                self.stranded = len;
+                                return Err(e);
+                            }
+                            // Part of the chunk has been written, so there's no going back
+                            Err(e) => return Err(io::Error::new(e.kind(), e)),
+                        }
+                    }
+                    Ok(len)
+                }
+            }
+        }
+    }
+
+    /// Returns the bytes [`copy_chunk`][Self::copy_chunk] took from the reader, but couldn't write,
+    /// before failing with an error which [`is_unsupported`][Self::is_unsupported]. They must be
+    /// written to the writer before copying the rest through a buffer.
+    pub(crate) fn take_stranded(&mut self) -> io::Result<Vec<u8>> {
+        let pipe_read = match self.method {
+            Method::Splice([pipe_read, _]) => pipe_read,
+            _ => return Ok(Vec::new()),
+        };
+        let mut buf = vec![0; self.stranded];
+        let mut filled = 0;
+        while filled < buf.len() {
+            // SAFETY: The pipe belongs to us, and `buf` has room for what's asked for.
+            let read = unsafe {
+                libc::read(
+                    pipe_read,
+                    buf[filled..].as_mut_ptr().cast(),
+                    buf.len() - filled,
+                )
+            };
+            match check(read) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        buf.truncate(filled);
+        self.stranded = 0;
+        Ok(buf)
+    }
+
+    /// Tests if `error`, returned by [`copy_chunk`][Self::copy_chunk], means the kernel can't copy
+    /// between this reader and writer, so they should be copied through a buffer instead.
+    pub(crate) fn is_unsupported(error: &io::Error) -> bool {
+        matches!(
+            error.raw_os_error(),
+            Some(libc::EINVAL | libc::ENOSYS | libc::EXDEV | libc::EOPNOTSUPP | libc::EBADF)
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for ZeroCopy {
+    fn drop(&mut self) {
+        if let Method::Splice(pipe) = self.method {
+            for fd in pipe {
+                // SAFETY: The pipe belongs to us, and isn't used again.
+                unsafe { libc::close(fd) };
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl ZeroCopy {
+    pub(crate) fn for_pair(_reader: &dyn Any, _writer: &dyn Any) -> Option<Self> {
+        None
+    }
+
+    pub(crate) fn copy_chunk(&mut self) -> std::io::Result<usize> {
+        match *self {}
+    }
+
+    pub(crate) fn take_stranded(&mut self) -> std::io::Result<Vec<u8>> {
+        match *self {}
+    }
+
+    pub(crate) fn is_unsupported(_error: &std::io::Error) -> bool {
+        true
+    }
+}